@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use penrose::{
+    core::{data_types::Region, xconnection::XConn, Hook},
+    PenroseError, Selector, WindowManager, Xid,
+};
+use xcb::Xid as _;
+
+use crate::{
+    handle_activation, intern_atom, is_fullscreen, set_fullscreen_from, update_urgency_from_hints,
+    ActivationPolicy, WindowManagerExt, X_DATA,
+};
+
+const NET_WM_STATE_REMOVE: u32 = 0;
+const NET_WM_STATE_ADD: u32 = 1;
+const NET_WM_STATE_TOGGLE: u32 = 2;
+
+/// Handles the EWMH messages clients send that penrose doesn't handle fully.
+///
+/// These are read from the `X_DATA` connection, which also gets them
+/// as it listens for substructure events on the root window.
+/// Property changes are only seen for clients the `UrgencyHook` has started listening to.
+///
+/// Penrose handles these messages too, before this hook sees them,
+/// so this puts right what it did rather than adding to it.
+/// The events arrive in order, so the geometry kept from configure events
+/// is still what windows had before penrose changed them.
+pub struct EwmhHook {
+    activation_policy: ActivationPolicy,
+    /// The region and border width of each window, from its configure events.
+    geometry: HashMap<Xid, (Region, u32)>,
+    /// The focus after the last event, before penrose handled this one.
+    focus: Focus,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+struct Focus {
+    screen: usize,
+    workspace: usize,
+    client: Option<Xid>,
+}
+
+impl Focus {
+    fn current<X: XConn>(wm: &WindowManager<X>) -> Self {
+        let screen = wm.active_screen_index();
+
+        Self {
+            screen,
+            workspace: wm
+                .screen(&Selector::Index(screen))
+                .map_or(0, |screen| screen.wix),
+            client: wm.focused_client_id(),
+        }
+    }
+
+    /// Puts the focus back here, if it's changed.
+    fn restore<X: XConn>(self, wm: &mut WindowManager<X>) -> penrose::Result<()> {
+        if Self::current(wm) == self {
+            return Ok(());
+        }
+
+        if let Some(client) = self.client {
+            if wm.client(&Selector::WinId(client)).is_some() {
+                return wm.focus_client_anywhere(client);
+            }
+        }
+
+        wm.focus_screen(self.screen)?;
+        wm.focus_workspace(&Selector::Index(self.workspace))
+    }
+}
+
+impl EwmhHook {
+    #[must_use]
+    pub fn new(activation_policy: ActivationPolicy) -> Box<Self> {
+        Box::new(Self {
+            activation_policy,
+            geometry: HashMap::new(),
+            focus: Focus::default(),
+        })
+    }
+
+    fn handle_event<X: XConn>(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        event: &xcb::Event,
+    ) -> penrose::Result<()> {
+        if let xcb::Event::X(xcb::x::Event::ConfigureNotify(notify)) = event {
+            let border = u32::from(notify.border_width());
+
+            // Penrose positions clients by their outer size, so match that here.
+            let region = Region::new(
+                notify.x().max(0).unsigned_abs().into(),
+                notify.y().max(0).unsigned_abs().into(),
+                u32::from(notify.width()) + border * 2,
+                u32::from(notify.height()) + border * 2,
+            );
+
+            self.geometry
+                .insert(notify.window().resource_id(), (region, border));
+        }
+
+        if let xcb::Event::X(xcb::x::Event::DestroyNotify(notify)) = event {
+            self.geometry.remove(&notify.window().resource_id());
+        }
+
+        if let xcb::Event::X(xcb::x::Event::PropertyNotify(notify)) = event {
+            if notify.atom() == xcb::x::ATOM_WM_HINTS {
+                update_urgency_from_hints(wm, notify.window().resource_id())?;
+            }
+        }
+
+        if let xcb::Event::X(xcb::x::Event::ClientMessage(message)) = event {
+            let data = match message.data() {
+                xcb::x::ClientMessageData::Data32(data) => data,
+                _ => return Ok(()),
+            };
+
+            let id = message.window().resource_id();
+
+            if Some(message.r#type()) == intern_atom("_NET_WM_STATE") {
+                let fullscreen = intern_atom("_NET_WM_STATE_FULLSCREEN")
+                    .map(|atom| atom.resource_id())
+                    .ok_or_else(|| {
+                        PenroseError::Raw("Couldn't intern _NET_WM_STATE_FULLSCREEN".into())
+                    })?;
+
+                if data[1] == fullscreen || data[2] == fullscreen {
+                    let geometry = self.geometry.get(&id).copied();
+
+                    match data[0] {
+                        NET_WM_STATE_REMOVE => set_fullscreen_from(wm, id, false, geometry)?,
+                        NET_WM_STATE_ADD => set_fullscreen_from(wm, id, true, geometry)?,
+                        NET_WM_STATE_TOGGLE => {
+                            set_fullscreen_from(wm, id, !is_fullscreen(id), geometry)?;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+
+            if Some(message.r#type()) == intern_atom("_NET_ACTIVE_WINDOW") {
+                // Penrose has already focused it, which only the policy should decide.
+                self.focus.restore(wm)?;
+                handle_activation(wm, id, self.activation_policy)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<X: XConn> Hook<X> for EwmhHook {
+    fn startup(&mut self, wm: &mut WindowManager<X>) -> penrose::Result<()> {
+        self.focus = Focus::current(wm);
+
+        X_DATA.conn.send_request(&xcb::x::ChangeWindowAttributes {
+            window: X_DATA.root,
+            value_list: &[xcb::x::Cw::EventMask(
+                xcb::x::EventMask::SUBSTRUCTURE_NOTIFY,
+            )],
+        });
+
+        let _ = X_DATA.conn.flush();
+
+        Ok(())
+    }
+
+    fn event_handled(&mut self, wm: &mut WindowManager<X>) -> penrose::Result<()> {
+        while let Ok(Some(event)) = X_DATA.conn.poll_for_event() {
+            if let Err(err) = self.handle_event(wm, &event) {
+                tracing::error!("Failed to handle EWMH event: {}", err);
+            }
+        }
+
+        self.focus = Focus::current(wm);
+
+        Ok(())
+    }
+}
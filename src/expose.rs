@@ -0,0 +1,136 @@
+use penrose::{
+    core::{data_types::Region, xconnection::XConn},
+    PenroseError, Selector, WindowManager, Xid,
+};
+
+use crate::{
+    hint_labels, is_mapped, region_center, region_contains, window_region, Overlay, OverlayInput,
+    WindowManagerExt, CLIENT_REGIONS,
+};
+
+/// Splits `region` into a grid with a cell for each of `count` windows, row by row.
+fn grid_regions(region: &Region, count: usize, gap_px: u32) -> Vec<Region> {
+    let columns = (count as f32).sqrt().ceil().max(1.) as u32;
+    let rows = (count as u32 + columns - 1) / columns;
+
+    let cell_w = region.w / columns;
+    let cell_h = region.h / rows.max(1);
+
+    (0..count as u32)
+        .map(|index| {
+            Region::new(
+                region.x + (index % columns) * cell_w + gap_px,
+                region.y + (index / columns) * cell_h + gap_px,
+                cell_w.saturating_sub(gap_px * 2),
+                cell_h.saturating_sub(gap_px * 2),
+            )
+        })
+        .collect()
+}
+
+/// Shows every window from every workspace in a grid on the focused screen,
+/// each labelled with a hint and its workspace.
+///
+/// Typing a hint or clicking a window goes to it, and escape goes back to how things were.
+/// Either way every window is first put back where it was,
+/// as penrose itself is never told that anything moved.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn expose<X: XConn>(wm: &mut WindowManager<X>, gap_px: u32) -> penrose::Result<()> {
+    let screen_region = wm
+        .screen(&Selector::Index(wm.active_screen_index()))
+        .ok_or(PenroseError::Raw("No focused screen".to_string()))?
+        .region(false);
+
+    let visible_workspaces = wm.focused_workspaces();
+
+    // Clients on visible workspaces that aren't mapped, like hidden scratchpads, are left alone.
+    let tiles = wm
+        .all_workspaces(&Selector::Any)
+        .into_iter()
+        .enumerate()
+        .flat_map(|(index, workspace)| {
+            workspace
+                .client_ids()
+                .into_iter()
+                .map(move |id| (id, index, workspace.name().to_string()))
+        })
+        .filter(|(id, index, _name)| !visible_workspaces.contains(index) || is_mapped(*id))
+        .collect::<Vec<_>>();
+
+    if tiles.is_empty() {
+        return Ok(());
+    }
+
+    // The layout puts tiled windows back, but everything else has to be remembered,
+    // including floating windows on workspaces that aren't showing.
+    let untiled_regions = {
+        let tiled_regions = CLIENT_REGIONS
+            .read()
+            .map_err(|_| PenroseError::Raw("CLIENT_REGIONS RwLock poisoned".into()))?;
+
+        tiles
+            .iter()
+            .filter(|(id, _index, _name)| !tiled_regions.contains_key(id))
+            .filter_map(|(id, _index, _name)| window_region(*id).map(|region| (*id, region)))
+            .collect::<Vec<_>>()
+    };
+
+    let grid = grid_regions(&screen_region, tiles.len(), gap_px);
+
+    for ((id, index, _name), region) in tiles.iter().zip(&grid) {
+        if !visible_workspaces.contains(index) {
+            wm.show_client(*id)?;
+        }
+
+        wm.position_client(*id, *region, true)?;
+    }
+
+    let picked = Overlay::new().and_then(|mut overlay| {
+        for (((_id, _index, name), region), hint) in
+            tiles.iter().zip(&grid).zip(hint_labels(tiles.len()))
+        {
+            overlay.add_label(region_center(region), hint, name);
+        }
+
+        match overlay.read_input() {
+            OverlayInput::Label(index) => Some(tiles[index].0),
+            OverlayInput::Click(point) => grid
+                .iter()
+                .position(|region| region_contains(region, point))
+                .map(|index| tiles[index].0),
+            OverlayInput::Cancel => None,
+        }
+    });
+
+    restore(wm, &tiles, &visible_workspaces, &untiled_regions)?;
+
+    match picked {
+        Some(id) => wm.focus_client_anywhere(id),
+        None => Ok(()),
+    }
+}
+
+fn restore<X: XConn>(
+    wm: &mut WindowManager<X>,
+    tiles: &[(Xid, usize, String)],
+    visible_workspaces: &[usize],
+    untiled_regions: &[(Xid, Region)],
+) -> penrose::Result<()> {
+    for (id, index, _name) in tiles {
+        if !visible_workspaces.contains(index) {
+            wm.hide_client(*id)?;
+        }
+    }
+
+    for (id, region) in untiled_regions {
+        wm.position_client(*id, *region, true)?;
+    }
+
+    for screen in 0..wm.n_screens() {
+        wm.layout_screen(screen)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,577 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use lazy_static::lazy_static;
+use penrose::{
+    core::{data_types::Region, xconnection::XConn},
+    PenroseError, Selector, WindowManager, Xid,
+};
+
+use crate::{
+    center_within, raise_window, region_center, window_region, SwitchDirection, WindowManagerExt,
+};
+
+/// How many pixels floating windows are moved by per keypress.
+pub const FLOATING_STEP: u32 = 40;
+
+/// The smallest a floating window can be shrunk to.
+const MIN_FLOATING_SIZE: u32 = 100;
+
+/// The sizes floating windows cycle through, as fractions of the screen.
+const PRESET_SIZES: [f32; 3] = [0.4, 0.6, 0.8];
+
+lazy_static! {
+    /// Where clients that were made floating last were, so they go back there when floated again.
+    static ref FLOATING_REGIONS: RwLock<HashMap<Xid, Region>> = RwLock::new(HashMap::new());
+}
+
+/// Part of a screen to snap a floating window to.
+#[derive(Clone, Copy)]
+pub enum Snap {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Snap {
+    /// This part of `screen`.
+    #[must_use]
+    pub const fn region_in(self, screen: &Region) -> Region {
+        let (half_w, half_h) = (screen.w / 2, screen.h / 2);
+        let (mid_x, mid_y) = (screen.x + half_w, screen.y + half_h);
+
+        let (x, y, w, h) = match self {
+            Self::Left => (screen.x, screen.y, half_w, screen.h),
+            Self::Right => (mid_x, screen.y, screen.w - half_w, screen.h),
+            Self::Top => (screen.x, screen.y, screen.w, half_h),
+            Self::Bottom => (screen.x, mid_y, screen.w, screen.h - half_h),
+            Self::TopLeft => (screen.x, screen.y, half_w, half_h),
+            Self::TopRight => (mid_x, screen.y, screen.w - half_w, half_h),
+            Self::BottomLeft => (screen.x, mid_y, half_w, screen.h - half_h),
+            Self::BottomRight => (mid_x, mid_y, screen.w - half_w, screen.h - half_h),
+        };
+
+        Region::new(x, y, w, h)
+    }
+}
+
+/// The region of the screen the center of `region` is on,
+/// falling back to the active screen.
+///
+/// # Errors
+/// Errors if there are no screens at all.
+pub fn screen_region_containing<X: XConn>(
+    wm: &WindowManager<X>,
+    region: &Region,
+) -> penrose::Result<Region> {
+    (0..wm.n_screens())
+        .filter_map(|index| wm.screen(&Selector::Index(index)))
+        .map(|screen| screen.region(false))
+        .find(|screen_region| center_within(region, screen_region))
+        .or_else(|| {
+            wm.screen(&Selector::Index(wm.active_screen_index()))
+                .map(|screen| screen.region(false))
+        })
+        .ok_or(PenroseError::Raw("No screens".to_string()))
+}
+
+/// Moves `region` as far as it can go by `step` in a direction without leaving `bounds`.
+#[must_use]
+pub const fn step_region(
+    region: Region,
+    bounds: &Region,
+    direction: SwitchDirection,
+    step: u32,
+) -> Region {
+    let mut region = region;
+
+    match direction {
+        SwitchDirection::Left => {
+            region.x = if region.x > bounds.x + step {
+                region.x - step
+            } else {
+                bounds.x
+            };
+        }
+        SwitchDirection::Up => {
+            region.y = if region.y > bounds.y + step {
+                region.y - step
+            } else {
+                bounds.y
+            };
+        }
+        SwitchDirection::Right => {
+            let max_x = (bounds.x + bounds.w).saturating_sub(region.w);
+            region.x = if region.x + step < max_x {
+                region.x + step
+            } else {
+                max_x
+            };
+        }
+        SwitchDirection::Down => {
+            let max_y = (bounds.y + bounds.h).saturating_sub(region.h);
+            region.y = if region.y + step < max_y {
+                region.y + step
+            } else {
+                max_y
+            };
+        }
+    }
+
+    region
+}
+
+/// Moves a floating client by a step, keeping it on its screen.
+///
+/// # Errors
+/// Errors if the client's geometry can't be found,
+/// or if an inner penrose command fails.
+pub fn move_floating<X: XConn>(
+    wm: &mut WindowManager<X>,
+    id: Xid,
+    direction: SwitchDirection,
+) -> penrose::Result<()> {
+    let region = window_region(id).ok_or(PenroseError::Raw(format!(
+        "Couldn't get the geometry of {id}"
+    )))?;
+
+    let bounds = screen_region_containing(wm, &region)?;
+
+    wm.position_client(
+        id,
+        step_region(region, &bounds, direction, FLOATING_STEP),
+        true,
+    )
+}
+
+/// Grows `region` by `step` towards a direction without leaving `bounds`.
+#[must_use]
+pub const fn grow_region(
+    region: Region,
+    bounds: &Region,
+    direction: SwitchDirection,
+    step: u32,
+) -> Region {
+    let mut region = region;
+
+    match direction {
+        SwitchDirection::Left => {
+            let new_x = if region.x > bounds.x + step {
+                region.x - step
+            } else {
+                bounds.x
+            };
+            region.w += region.x - new_x;
+            region.x = new_x;
+        }
+        SwitchDirection::Up => {
+            let new_y = if region.y > bounds.y + step {
+                region.y - step
+            } else {
+                bounds.y
+            };
+            region.h += region.y - new_y;
+            region.y = new_y;
+        }
+        SwitchDirection::Right => {
+            let max_w = (bounds.x + bounds.w).saturating_sub(region.x);
+            region.w = if region.w + step < max_w {
+                region.w + step
+            } else {
+                max_w
+            };
+        }
+        SwitchDirection::Down => {
+            let max_h = (bounds.y + bounds.h).saturating_sub(region.y);
+            region.h = if region.h + step < max_h {
+                region.h + step
+            } else {
+                max_h
+            };
+        }
+    }
+
+    region
+}
+
+/// Grows a floating client by a step towards a direction, keeping it on its screen.
+///
+/// # Errors
+/// Errors if the client's geometry can't be found,
+/// or if an inner penrose command fails.
+pub fn grow_floating<X: XConn>(
+    wm: &mut WindowManager<X>,
+    id: Xid,
+    direction: SwitchDirection,
+) -> penrose::Result<()> {
+    let region = window_region(id).ok_or(PenroseError::Raw(format!(
+        "Couldn't get the geometry of {id}"
+    )))?;
+
+    let bounds = screen_region_containing(wm, &region)?;
+
+    wm.position_client(
+        id,
+        grow_region(region, &bounds, direction, FLOATING_STEP),
+        true,
+    )
+}
+
+/// Shrinks `region` by `step` from the edge in a direction, keeping it at least the minimum size.
+#[must_use]
+pub const fn shrink_region(region: Region, direction: SwitchDirection, step: u32) -> Region {
+    let mut region = region;
+
+    match direction {
+        SwitchDirection::Left | SwitchDirection::Right => {
+            let new_w = if region.w > MIN_FLOATING_SIZE + step {
+                region.w - step
+            } else {
+                MIN_FLOATING_SIZE
+            };
+
+            if matches!(direction, SwitchDirection::Left) && region.w > new_w {
+                region.x += region.w - new_w;
+            }
+            region.w = new_w;
+        }
+        SwitchDirection::Up | SwitchDirection::Down => {
+            let new_h = if region.h > MIN_FLOATING_SIZE + step {
+                region.h - step
+            } else {
+                MIN_FLOATING_SIZE
+            };
+
+            if matches!(direction, SwitchDirection::Up) && region.h > new_h {
+                region.y += region.h - new_h;
+            }
+            region.h = new_h;
+        }
+    }
+
+    region
+}
+
+/// Pulls the edge of a floating client in a direction back in by a step.
+///
+/// # Errors
+/// Errors if the client's geometry can't be found,
+/// or if an inner penrose command fails.
+pub fn shrink_floating<X: XConn>(
+    wm: &mut WindowManager<X>,
+    id: Xid,
+    direction: SwitchDirection,
+) -> penrose::Result<()> {
+    let region = window_region(id).ok_or(PenroseError::Raw(format!(
+        "Couldn't get the geometry of {id}"
+    )))?;
+
+    wm.position_client(id, shrink_region(region, direction, FLOATING_STEP), true)
+}
+
+/// Whether a client is laid out by penrose.
+///
+/// # Errors
+/// Errors if the `CLIENT_REGIONS` lock is poisoned.
+pub fn is_tiled<X: XConn>(wm: &WindowManager<X>, id: Xid) -> penrose::Result<bool> {
+    Ok(wm.visible_client_regions()?.contains_key(&id))
+}
+
+/// Takes a client out of the layout and floats it where it was last floated,
+/// or in the middle of where it was tiled.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn make_floating<X: XConn>(wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+    let tiled_region = wm.visible_client_regions()?.get(&id).copied();
+
+    let region = FLOATING_REGIONS
+        .read()
+        .map_err(|_| PenroseError::Raw("FLOATING_REGIONS RwLock poisoned".into()))?
+        .get(&id)
+        .copied()
+        .or_else(|| {
+            tiled_region.and_then(|tile| tile.scale_w(0.8).scale_h(0.8).centered_in(&tile).ok())
+        })
+        .or_else(|| window_region(id))
+        .ok_or(PenroseError::Raw(format!(
+            "Couldn't get the geometry of {id}"
+        )))?;
+
+    if let Some(client) = wm.client_mut(&Selector::WinId(id)) {
+        client.externally_managed();
+    }
+
+    wm.layout_client_screen(id)?;
+    wm.position_client(id, region, true)?;
+    raise_window(id);
+
+    Ok(())
+}
+
+/// Puts a floating client back into the layout.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn make_tiled<X: XConn>(wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+    if let Some(region) = window_region(id) {
+        FLOATING_REGIONS
+            .write()
+            .map_err(|_| PenroseError::Raw("FLOATING_REGIONS RwLock poisoned".into()))?
+            .insert(id, region);
+    }
+
+    if let Some(client) = wm.client_mut(&Selector::WinId(id)) {
+        client.internally_managed();
+    }
+
+    wm.layout_client_screen(id)
+}
+
+/// Switches the focused client between floating and tiled.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn toggle_floating<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    let id = match wm.focused_client_id() {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    if is_tiled(wm, id)? {
+        make_floating(wm, id)
+    } else {
+        make_tiled(wm, id)
+    }
+}
+
+/// Runs `f` on the focused client's region and moves it to the result,
+/// floating the client first if it was tiled.
+fn reposition_focused<X: XConn>(
+    wm: &mut WindowManager<X>,
+    f: impl FnOnce(Region, &Region) -> Region,
+) -> penrose::Result<()> {
+    let id = match wm.focused_client_id() {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    if is_tiled(wm, id)? {
+        make_floating(wm, id)?;
+    }
+
+    let region = window_region(id).ok_or(PenroseError::Raw(format!(
+        "Couldn't get the geometry of {id}"
+    )))?;
+
+    let screen = screen_region_containing(wm, &region)?;
+
+    wm.position_client(id, f(region, &screen), true)
+}
+
+/// Puts the focused client in the middle of its screen.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn center_floating<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    reposition_focused(wm, |region, screen| {
+        region.centered_in(screen).unwrap_or(region)
+    })
+}
+
+/// Snaps the focused client to half or a quarter of its screen.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn snap_floating<X: XConn>(wm: &mut WindowManager<X>, snap: Snap) -> penrose::Result<()> {
+    reposition_focused(wm, |_region, screen| snap.region_in(screen))
+}
+
+/// Cycles the focused client through the preset sizes, keeping it where it is.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn cycle_floating_size<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    reposition_focused(wm, |region, screen| {
+        let current = region.w as f32 / screen.w as f32;
+
+        // The first preset that's bigger than it is now, going back to the start after the last.
+        let next = PRESET_SIZES
+            .iter()
+            .find(|size| **size > current + 0.01)
+            .unwrap_or(&PRESET_SIZES[0]);
+
+        let resized = screen.scale_w(*next).scale_h(*next);
+
+        let (center_x, center_y) = region_center(&region);
+        let x = center_x
+            .saturating_sub(resized.w / 2)
+            .clamp(screen.x, screen.x + screen.w - resized.w);
+        let y = center_y
+            .saturating_sub(resized.h / 2)
+            .clamp(screen.y, screen.y + screen.h - resized.h);
+
+        Region::new(x, y, resized.w, resized.h)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOUNDS: Region = Region {
+        x: 1000,
+        y: 0,
+        w: 1000,
+        h: 800,
+    };
+
+    #[test]
+    fn step_region_moves_by_step() {
+        let region = Region::new(1200, 200, 300, 300);
+
+        assert_eq!(
+            step_region(region, &BOUNDS, SwitchDirection::Left, 40),
+            Region::new(1160, 200, 300, 300)
+        );
+        assert_eq!(
+            step_region(region, &BOUNDS, SwitchDirection::Down, 40),
+            Region::new(1200, 240, 300, 300)
+        );
+    }
+
+    #[test]
+    fn step_region_stops_at_bounds() {
+        let region = Region::new(1020, 10, 300, 300);
+
+        assert_eq!(
+            step_region(region, &BOUNDS, SwitchDirection::Left, 40),
+            Region::new(1000, 10, 300, 300)
+        );
+        assert_eq!(
+            step_region(region, &BOUNDS, SwitchDirection::Up, 40),
+            Region::new(1020, 0, 300, 300)
+        );
+
+        let region = Region::new(1680, 480, 300, 300);
+
+        assert_eq!(
+            step_region(region, &BOUNDS, SwitchDirection::Right, 40),
+            Region::new(1700, 480, 300, 300)
+        );
+        assert_eq!(
+            step_region(region, &BOUNDS, SwitchDirection::Down, 40),
+            Region::new(1680, 500, 300, 300)
+        );
+    }
+
+    #[test]
+    fn grow_region_moves_the_edge_in_direction() {
+        let region = Region::new(1200, 200, 300, 300);
+
+        assert_eq!(
+            grow_region(region, &BOUNDS, SwitchDirection::Left, 40),
+            Region::new(1160, 200, 340, 300)
+        );
+        assert_eq!(
+            grow_region(region, &BOUNDS, SwitchDirection::Up, 40),
+            Region::new(1200, 160, 300, 340)
+        );
+        assert_eq!(
+            grow_region(region, &BOUNDS, SwitchDirection::Right, 40),
+            Region::new(1200, 200, 340, 300)
+        );
+        assert_eq!(
+            grow_region(region, &BOUNDS, SwitchDirection::Down, 40),
+            Region::new(1200, 200, 300, 340)
+        );
+    }
+
+    #[test]
+    fn grow_region_stops_at_bounds() {
+        let region = Region::new(1020, 10, 960, 780);
+
+        assert_eq!(
+            grow_region(region, &BOUNDS, SwitchDirection::Left, 40),
+            Region::new(1000, 10, 980, 780)
+        );
+        assert_eq!(
+            grow_region(region, &BOUNDS, SwitchDirection::Right, 40),
+            Region::new(1020, 10, 980, 780)
+        );
+        assert_eq!(
+            grow_region(region, &BOUNDS, SwitchDirection::Down, 40),
+            Region::new(1020, 10, 960, 790)
+        );
+    }
+
+    #[test]
+    fn shrink_region_pulls_in_the_edge_in_direction() {
+        let region = Region::new(1200, 200, 300, 300);
+
+        assert_eq!(
+            shrink_region(region, SwitchDirection::Left, 40),
+            Region::new(1240, 200, 260, 300)
+        );
+        assert_eq!(
+            shrink_region(region, SwitchDirection::Right, 40),
+            Region::new(1200, 200, 260, 300)
+        );
+        assert_eq!(
+            shrink_region(region, SwitchDirection::Up, 40),
+            Region::new(1200, 240, 300, 260)
+        );
+        assert_eq!(
+            shrink_region(region, SwitchDirection::Down, 40),
+            Region::new(1200, 200, 300, 260)
+        );
+    }
+
+    #[test]
+    fn shrink_region_keeps_the_minimum_size() {
+        let region = Region::new(1200, 200, 120, 120);
+
+        assert_eq!(
+            shrink_region(region, SwitchDirection::Left, 40),
+            Region::new(1220, 200, MIN_FLOATING_SIZE, 120)
+        );
+        assert_eq!(
+            shrink_region(region, SwitchDirection::Down, 40),
+            Region::new(1200, 200, 120, MIN_FLOATING_SIZE)
+        );
+    }
+
+    #[test]
+    fn snap_regions_cover_the_screen() {
+        let screen = Region::new(1000, 0, 1001, 801);
+
+        assert_eq!(
+            Snap::Left.region_in(&screen),
+            Region::new(1000, 0, 500, 801)
+        );
+        assert_eq!(
+            Snap::Right.region_in(&screen),
+            Region::new(1500, 0, 501, 801)
+        );
+        assert_eq!(
+            Snap::Top.region_in(&screen),
+            Region::new(1000, 0, 1001, 400)
+        );
+        assert_eq!(
+            Snap::Bottom.region_in(&screen),
+            Region::new(1000, 400, 1001, 401)
+        );
+        assert_eq!(
+            Snap::TopLeft.region_in(&screen),
+            Region::new(1000, 0, 500, 400)
+        );
+        assert_eq!(
+            Snap::BottomRight.region_in(&screen),
+            Region::new(1500, 400, 501, 401)
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    sync::{Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use penrose::{
+    core::{ring::Direction, xconnection::XConn, Hook},
+    PenroseError, Selector, WindowManager, Xid,
+};
+
+use crate::{is_hidden, WindowManagerExt};
+
+/// How long after the last press an alt-tab cycle carries on from where it was.
+const CYCLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+lazy_static! {
+    /// Every client on every workspace, most recently focused first.
+    pub static ref FOCUS_HISTORY: RwLock<Vec<Xid>> = RwLock::new(Vec::new());
+
+    static ref CYCLE: Mutex<Option<FocusCycle>> = Mutex::new(None);
+}
+
+/// An alt-tab cycle through the focus history as it was when the cycle started.
+struct FocusCycle {
+    clients: Vec<Xid>,
+    position: usize,
+    last_press: Instant,
+}
+
+impl FocusCycle {
+    fn is_active(&self) -> bool {
+        self.last_press.elapsed() < CYCLE_TIMEOUT
+    }
+}
+
+/// Whether an alt-tab cycle is going on, with its preview in the root window name.
+#[must_use]
+pub fn focus_cycle_active() -> bool {
+    CYCLE.lock().map_or(false, |cycle| {
+        cycle.as_ref().map_or(false, FocusCycle::is_active)
+    })
+}
+
+fn history_poisoned<T>(_: T) -> PenroseError {
+    PenroseError::Raw("FOCUS_HISTORY RwLock poisoned".into())
+}
+
+fn cycle_poisoned<T>(_: T) -> PenroseError {
+    PenroseError::Raw("CYCLE Mutex poisoned".into())
+}
+
+/// Records every focus change and forgets clients when they close.
+pub struct FocusHistoryHook {}
+
+impl FocusHistoryHook {
+    #[must_use]
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+}
+
+impl<X: XConn> Hook<X> for FocusHistoryHook {
+    fn focus_change(&mut self, _wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        {
+            let mut history = FOCUS_HISTORY.write().map_err(history_poisoned)?;
+            history.retain(|other| *other != id);
+            history.insert(0, id);
+        }
+
+        let mut cycle = CYCLE.lock().map_err(cycle_poisoned)?;
+
+        // The focus moved on from the cycle, so the status can replace its preview.
+        if cycle.as_ref().map_or(false, |cycle| !cycle.is_active()) {
+            *cycle = None;
+        }
+
+        Ok(())
+    }
+
+    fn remove_client(&mut self, _wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        FOCUS_HISTORY
+            .write()
+            .map_err(history_poisoned)?
+            .retain(|other| *other != id);
+
+        if let Some(cycle) = CYCLE.lock().map_err(cycle_poisoned)?.as_mut() {
+            cycle.clients.retain(|other| *other != id);
+            cycle.position = cycle.position.min(cycle.clients.len().saturating_sub(1));
+        }
+
+        Ok(())
+    }
+}
+
+/// Toggles between the focused window and the one focused before it.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn focus_previous_window<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    let previous = FOCUS_HISTORY
+        .read()
+        .map_err(history_poisoned)?
+        .iter()
+        .skip(1)
+        .find(|id| !is_hidden(**id))
+        .copied();
+
+    match previous {
+        Some(id) => wm.focus_client_anywhere(id),
+        None => Ok(()),
+    }
+}
+
+/// Alt-tab through the windows in the order they were last focused,
+/// switching workspaces as needed.
+///
+/// Presses close together carry on the same cycle,
+/// with a preview of it shown in the root window name.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn cycle_focus_history<X: XConn>(
+    wm: &mut WindowManager<X>,
+    direction: Direction,
+) -> penrose::Result<()> {
+    let (target, preview, clients) = {
+        let mut cycle = CYCLE.lock().map_err(cycle_poisoned)?;
+
+        if !cycle.as_ref().map_or(false, FocusCycle::is_active) {
+            *cycle = Some(FocusCycle {
+                clients: FOCUS_HISTORY
+                    .read()
+                    .map_err(history_poisoned)?
+                    .iter()
+                    .copied()
+                    .filter(|id| !is_hidden(*id))
+                    .collect(),
+                position: 0,
+                last_press: Instant::now(),
+            });
+        }
+
+        let cycle = cycle.as_mut().unwrap_or_else(|| unreachable!());
+
+        if cycle.clients.len() < 2 {
+            return Ok(());
+        }
+
+        let len = cycle.clients.len();
+        cycle.position = match direction {
+            Direction::Forward => (cycle.position + 1) % len,
+            Direction::Backward => (cycle.position + len - 1) % len,
+        };
+        cycle.last_press = Instant::now();
+
+        let preview = cycle
+            .clients
+            .iter()
+            .enumerate()
+            .map(|(index, id)| {
+                let class = wm
+                    .client(&Selector::WinId(*id))
+                    .map_or("?", |client| client.wm_class());
+
+                if index == cycle.position {
+                    format!("[{class}]")
+                } else {
+                    class.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        (
+            cycle.clients[cycle.position],
+            preview,
+            cycle.clients.clone(),
+        )
+    };
+
+    wm.focus_client_anywhere(target)?;
+
+    // Keep the rest of the history as it was when the cycle started,
+    // so that finishing the cycle is just like picking that window.
+    *FOCUS_HISTORY.write().map_err(history_poisoned)? = std::iter::once(target)
+        .chain(clients.into_iter().filter(|id| *id != target))
+        .collect();
+
+    wm.set_root_window_name(&preview)
+}
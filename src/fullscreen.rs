@@ -0,0 +1,160 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use lazy_static::lazy_static;
+use penrose::{
+    core::{data_types::Region, xconnection::XConn, Hook},
+    PenroseError, Selector, WindowManager, Xid,
+};
+
+use crate::{
+    add_net_wm_state, border_width, configure_window, is_tiled, remove_net_wm_state,
+    screen_region_containing, window_region, WindowManagerExt,
+};
+
+lazy_static! {
+    /// Fullscreen clients, and what they were like before.
+    static ref FULLSCREEN: RwLock<HashMap<Xid, BeforeFullscreen>> = RwLock::new(HashMap::new());
+}
+
+#[derive(Clone, Copy)]
+struct BeforeFullscreen {
+    border: u32,
+    /// The region of a floating client, or `None` if it was tiled.
+    floating_region: Option<Region>,
+}
+
+fn fullscreen_poisoned<T>(_: T) -> PenroseError {
+    PenroseError::Raw("FULLSCREEN RwLock poisoned".into())
+}
+
+/// Whether a client is fullscreen.
+#[must_use]
+pub fn is_fullscreen(id: Xid) -> bool {
+    FULLSCREEN
+        .read()
+        .map_or(false, |fullscreen| fullscreen.contains_key(&id))
+}
+
+/// Makes a client cover its whole screen with no border or gaps and above everything else,
+/// or puts it back where it was.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn set_fullscreen<X: XConn>(
+    wm: &mut WindowManager<X>,
+    id: Xid,
+    fullscreen: bool,
+) -> penrose::Result<()> {
+    set_fullscreen_from(wm, id, fullscreen, None)
+}
+
+/// Like `set_fullscreen`, but with the region and border width the client had
+/// before something else, like penrose, started changing it.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn set_fullscreen_from<X: XConn>(
+    wm: &mut WindowManager<X>,
+    id: Xid,
+    fullscreen: bool,
+    geometry: Option<(Region, u32)>,
+) -> penrose::Result<()> {
+    if fullscreen == is_fullscreen(id) {
+        return Ok(());
+    }
+
+    if fullscreen {
+        let (region, border) = match geometry {
+            Some(geometry) => geometry,
+            None => (
+                window_region(id).ok_or(PenroseError::Raw(format!(
+                    "Couldn't get the geometry of {id}"
+                )))?,
+                border_width(id).unwrap_or(0),
+            ),
+        };
+
+        let before = BeforeFullscreen {
+            border,
+            floating_region: if is_tiled(wm, id)? {
+                None
+            } else {
+                Some(region)
+            },
+        };
+
+        let screen = screen_region_containing(wm, &region)?;
+
+        FULLSCREEN
+            .write()
+            .map_err(fullscreen_poisoned)?
+            .insert(id, before);
+
+        if let Some(client) = wm.client_mut(&Selector::WinId(id)) {
+            client.externally_managed();
+        }
+        wm.layout_client_screen(id)?;
+
+        configure_window(
+            id,
+            &[
+                xcb::x::ConfigWindow::X(screen.x as i32),
+                xcb::x::ConfigWindow::Y(screen.y as i32),
+                xcb::x::ConfigWindow::Width(screen.w),
+                xcb::x::ConfigWindow::Height(screen.h),
+                xcb::x::ConfigWindow::BorderWidth(0),
+                xcb::x::ConfigWindow::StackMode(xcb::x::StackMode::Above),
+            ],
+        );
+        add_net_wm_state(id, "_NET_WM_STATE_FULLSCREEN");
+    } else {
+        let before = FULLSCREEN.write().map_err(fullscreen_poisoned)?.remove(&id);
+
+        remove_net_wm_state(id, "_NET_WM_STATE_FULLSCREEN");
+
+        if let Some(before) = before {
+            configure_window(id, &[xcb::x::ConfigWindow::BorderWidth(before.border)]);
+
+            match before.floating_region {
+                Some(region) => wm.position_client(id, region, true)?,
+                None => {
+                    if let Some(client) = wm.client_mut(&Selector::WinId(id)) {
+                        client.internally_managed();
+                    }
+                    wm.layout_client_screen(id)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Switches the focused client in and out of fullscreen.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn toggle_fullscreen<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    match wm.focused_client_id() {
+        Some(id) => set_fullscreen(wm, id, !is_fullscreen(id)),
+        None => Ok(()),
+    }
+}
+
+/// Forgets fullscreen clients when they close.
+pub struct FullscreenHook {}
+
+impl FullscreenHook {
+    #[must_use]
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+}
+
+impl<X: XConn> Hook<X> for FullscreenHook {
+    fn remove_client(&mut self, _wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        FULLSCREEN.write().map_err(fullscreen_poisoned)?.remove(&id);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use lazy_static::lazy_static;
+use penrose::{
+    core::data_types::{Point, Region},
+    Xid,
+};
+
+lazy_static! {
+    pub static ref CLIENT_REGIONS: RwLock<HashMap<Xid, Region>> = RwLock::new(HashMap::new());
+}
+
+#[derive(Clone, Copy)]
+pub enum SwitchDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[must_use]
+pub const fn region_center(region: &Region) -> (u32, u32) {
+    (region.x + (region.w / 2), region.y + (region.h / 2))
+}
+
+/// How far `to` is from `from` when travelling in `direction`.
+///
+/// The first value is the gap between the facing edges,
+/// the second is how far apart the centers are on the other axis.
+/// Comparing these as a tuple prefers touching regions, then the most lined up one.
+///
+/// Returns `None` if `to` is not entirely in `direction` from `from`.
+#[must_use]
+pub fn directional_distance(
+    from: &Region,
+    to: &Region,
+    direction: SwitchDirection,
+) -> Option<(u32, u32)> {
+    let (from_x, from_y) = region_center(from);
+    let (to_x, to_y) = region_center(to);
+
+    match direction {
+        SwitchDirection::Left if to.x + to.w <= from.x => {
+            Some((from.x - (to.x + to.w), from_y.abs_diff(to_y)))
+        }
+        SwitchDirection::Right if from.x + from.w <= to.x => {
+            Some((to.x - (from.x + from.w), from_y.abs_diff(to_y)))
+        }
+        SwitchDirection::Up if to.y + to.h <= from.y => {
+            Some((from.y - (to.y + to.h), from_x.abs_diff(to_x)))
+        }
+        SwitchDirection::Down if from.y + from.h <= to.y => {
+            Some((to.y - (from.y + from.h), from_x.abs_diff(to_x)))
+        }
+        _ => None,
+    }
+}
+
+/// Finds the region closest to `from` in `direction`.
+#[must_use]
+pub fn nearest_in_direction<T>(
+    from: &Region,
+    candidates: impl IntoIterator<Item = (T, Region)>,
+    direction: SwitchDirection,
+) -> Option<(T, Region)> {
+    candidates
+        .into_iter()
+        .filter_map(|(item, region)| {
+            directional_distance(from, &region, direction).map(|dst| (dst, item, region))
+        })
+        .min_by_key(|(dst, _, _)| *dst)
+        .map(|(_, item, region)| (item, region))
+}
+
+/// Whether the center of `inner` is inside `outer`.
+#[must_use]
+pub const fn center_within(inner: &Region, outer: &Region) -> bool {
+    let (x, y) = region_center(inner);
+
+    x >= outer.x && x < outer.x + outer.w && y >= outer.y && y < outer.y + outer.h
+}
+
+/// Whether `point` is inside `region`.
+#[must_use]
+pub const fn region_contains(region: &Region, point: Point) -> bool {
+    point.x >= region.x
+        && point.x < region.x + region.w
+        && point.y >= region.y
+        && point.y < region.y + region.h
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directional_distance_measures_gap_then_offset() {
+        let from = Region::new(100, 100, 100, 100);
+
+        assert_eq!(
+            directional_distance(&from, &Region::new(0, 120, 90, 100), SwitchDirection::Left),
+            Some((10, 20))
+        );
+        assert_eq!(
+            directional_distance(
+                &from,
+                &Region::new(200, 100, 50, 100),
+                SwitchDirection::Right
+            ),
+            Some((0, 0))
+        );
+        assert_eq!(
+            directional_distance(&from, &Region::new(130, 0, 100, 50), SwitchDirection::Up),
+            Some((50, 30))
+        );
+        assert_eq!(
+            directional_distance(
+                &from,
+                &Region::new(100, 250, 100, 100),
+                SwitchDirection::Down
+            ),
+            Some((50, 0))
+        );
+    }
+
+    #[test]
+    fn directional_distance_ignores_overlapping_regions() {
+        let from = Region::new(100, 100, 100, 100);
+        let overlapping = Region::new(150, 150, 100, 100);
+
+        for direction in [
+            SwitchDirection::Up,
+            SwitchDirection::Down,
+            SwitchDirection::Left,
+            SwitchDirection::Right,
+        ] {
+            assert_eq!(directional_distance(&from, &overlapping, direction), None);
+        }
+
+        assert_eq!(
+            directional_distance(
+                &from,
+                &Region::new(300, 100, 100, 100),
+                SwitchDirection::Left
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn nearest_in_direction_prefers_touching_then_lined_up() {
+        let from = Region::new(100, 100, 100, 100);
+        let candidates = vec![
+            ("far", Region::new(300, 100, 100, 100)),
+            ("touching", Region::new(200, 150, 100, 100)),
+            ("lined up", Region::new(200, 100, 100, 100)),
+            ("left", Region::new(0, 100, 100, 100)),
+        ];
+
+        assert_eq!(
+            nearest_in_direction(&from, candidates.clone(), SwitchDirection::Right)
+                .map(|(name, _region)| name),
+            Some("lined up")
+        );
+        assert_eq!(
+            nearest_in_direction(&from, candidates.clone(), SwitchDirection::Left)
+                .map(|(name, _region)| name),
+            Some("left")
+        );
+        assert_eq!(
+            nearest_in_direction(&from, candidates, SwitchDirection::Up),
+            None
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use penrose::{
+    core::{ring::InsertPoint, xconnection::XConn, Hook},
+    PenroseError, Selector, WindowManager, Xid,
+};
+
+use crate::{add_net_wm_state, is_swallowed, remove_net_wm_state, PickerQueue};
+
+lazy_static! {
+    /// Clients that have been hidden, in the order they were hidden.
+    static ref HIDDEN: RwLock<Vec<Xid>> = RwLock::new(Vec::new());
+}
+
+fn hidden_poisoned<T>(_: T) -> PenroseError {
+    PenroseError::Raw("HIDDEN RwLock poisoned".into())
+}
+
+/// Whether a client is out of sight on purpose, having been hidden with `hide_focused`
+/// or swallowed by a window opened from it, so it shouldn't be focused.
+#[must_use]
+pub fn is_hidden(id: Xid) -> bool {
+    is_swallowed(id) || HIDDEN.read().map_or(false, |hidden| hidden.contains(&id))
+}
+
+/// Unmaps the focused client and takes it out of the layout without killing it.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn hide_focused<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    let id = match wm.focused_client_id() {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    let workspace = match wm.client(&Selector::WinId(id)) {
+        Some(client) => client.workspace(),
+        None => return Ok(()),
+    };
+
+    if let Some(ws) = wm.workspace_mut(&Selector::Index(workspace)) {
+        ws.remove_client(id);
+    }
+
+    HIDDEN.write().map_err(hidden_poisoned)?.push(id);
+
+    wm.hide_client(id)?;
+    add_net_wm_state(id, "_NET_WM_STATE_HIDDEN");
+
+    wm.layout_screen(wm.active_screen_index())?;
+
+    if let Some(next) = wm.active_workspace().focused_client() {
+        wm.focus_client(&Selector::WinId(next))?;
+    }
+
+    Ok(())
+}
+
+/// Brings a hidden client back onto the active workspace and focuses it.
+///
+/// Does nothing if the client has closed since it was hidden.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn restore_hidden<X: XConn>(wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+    HIDDEN
+        .write()
+        .map_err(hidden_poisoned)?
+        .retain(|other| *other != id);
+
+    if wm.client(&Selector::WinId(id)).is_none() {
+        return Ok(());
+    }
+
+    let workspace = wm
+        .screen(&Selector::Index(wm.active_screen_index()))
+        .ok_or(PenroseError::Raw("No active screen".to_string()))?
+        .wix;
+
+    wm.workspace_mut(&Selector::Index(workspace))
+        .ok_or(PenroseError::Raw(format!("No workspace {workspace}")))?
+        .add_client(id, &InsertPoint::First)?;
+
+    if let Some(client) = wm.client_mut(&Selector::WinId(id)) {
+        client.set_workspace(workspace);
+    }
+
+    remove_net_wm_state(id, "_NET_WM_STATE_HIDDEN");
+    wm.show_client(id)?;
+
+    wm.layout_screen(wm.active_screen_index())?;
+    wm.focus_client(&Selector::WinId(id))?;
+
+    Ok(())
+}
+
+/// Lists the hidden clients in the picker, most recently hidden first,
+/// and restores the one picked.
+pub fn restore_hidden_picker<X: XConn + 'static>(wm: &WindowManager<X>, pickers: &PickerQueue<X>) {
+    let hidden = HIDDEN
+        .read()
+        .map(|hidden| {
+            hidden
+                .iter()
+                .rev()
+                .copied()
+                .filter(|id| wm.client(&Selector::WinId(*id)).is_some())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let lines = hidden
+        .iter()
+        .enumerate()
+        .map(|(index, id)| match wm.client(&Selector::WinId(*id)) {
+            Some(client) => format!("{index}: {} - {}", client.wm_class(), client.wm_name()),
+            None => format!("{index}: {id}"),
+        })
+        .collect::<Vec<_>>();
+
+    pickers.pick_index("restore", lines, move |wm, index| {
+        restore_hidden(wm, hidden[index])
+    });
+}
+
+/// Forgets hidden clients when they close.
+pub struct HiddenHook {}
+
+impl HiddenHook {
+    #[must_use]
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+}
+
+impl<X: XConn> Hook<X> for HiddenHook {
+    fn remove_client(&mut self, _wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        HIDDEN
+            .write()
+            .map_err(hidden_poisoned)?
+            .retain(|other| *other != id);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use penrose::{core::xconnection::XConn, PenroseError, Selector, WindowManager};
+
+use crate::{
+    hint_labels, is_mapped, region_center, region_contains, window_region, Overlay, OverlayInput,
+    WindowManagerExt, CLIENT_REGIONS,
+};
+
+/// What happens to the window picked with a hint.
+#[derive(Clone, Copy)]
+pub enum HintAction {
+    Focus,
+    /// Swap it with the focused window.
+    Swap,
+    Close,
+}
+
+/// Labels every window on every screen with a hint,
+/// then does `action` to the one whose hint is typed or which is clicked.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn hint_windows<X: XConn>(
+    wm: &mut WindowManager<X>,
+    action: HintAction,
+) -> penrose::Result<()> {
+    let tiled_regions = CLIENT_REGIONS
+        .read()
+        .map_err(|_| PenroseError::Raw("CLIENT_REGIONS RwLock poisoned".into()))?
+        .clone();
+
+    let windows = wm
+        .focused_workspaces()
+        .into_iter()
+        .filter_map(|index| wm.workspace(&Selector::Index(index)))
+        .flat_map(|workspace| workspace.client_ids())
+        .filter(|id| is_mapped(*id))
+        .filter_map(|id| {
+            tiled_regions
+                .get(&id)
+                .copied()
+                .or_else(|| window_region(id))
+                .map(|region| (id, region))
+        })
+        .collect::<Vec<_>>();
+
+    if windows.is_empty() {
+        return Ok(());
+    }
+
+    let picked = Overlay::new().and_then(|mut overlay| {
+        for ((_id, region), hint) in windows.iter().zip(hint_labels(windows.len())) {
+            overlay.add_label(region_center(region), hint, "");
+        }
+
+        match overlay.read_input() {
+            OverlayInput::Label(index) => Some(windows[index].0),
+            // Floating windows are in front, so check them first.
+            OverlayInput::Click(point) => windows
+                .iter()
+                .filter(|(_id, region)| region_contains(region, point))
+                .min_by_key(|(id, _region)| tiled_regions.contains_key(id))
+                .map(|(id, _region)| *id),
+            OverlayInput::Cancel => None,
+        }
+    });
+
+    let id = match picked {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    match action {
+        HintAction::Focus => wm.focus_client_anywhere(id),
+        HintAction::Swap => match wm.focused_client_id() {
+            Some(focused) if focused != id => wm.swap_clients(focused, id),
+            _ => Ok(()),
+        },
+        HintAction::Close => {
+            // Penrose can only close the focused client.
+            wm.focus_client_anywhere(id)?;
+            wm.kill_client()
+        }
+    }
+}
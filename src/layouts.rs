@@ -1,10 +1,129 @@
-use penrose::core::{
-    data_types::{Region, ResizeAction},
-    Client, Layout, LayoutConf,
+use lazy_static::lazy_static;
+use penrose::{
+    core::{
+        data_types::{Region, ResizeAction},
+        xconnection::XConn,
+        Client, Hook, Layout, LayoutConf,
+    },
+    PenroseError, WindowManager, Xid,
 };
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::RwLock};
 
-use crate::CLIENT_REGIONS;
+use crate::{center_within, CLIENT_REGIONS};
+
+/// How much a split ratio changes per keypress.
+pub const RATIO_STEP: f32 = 0.05;
+
+lazy_static! {
+    /// The splits made by the last layout of each screen.
+    pub static ref LAYOUT_SPLITS: RwLock<Vec<LayoutSplit>> = RwLock::new(Vec::new());
+
+    /// Split ratios that have been resized.
+    pub static ref SPLIT_RATIOS: RwLock<HashMap<SplitKey, f32>> = RwLock::new(HashMap::new());
+}
+
+/// A layout name and what a split it makes belongs to.
+pub type SplitKey = (&'static str, SplitOwner);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SplitOwner {
+    /// The client on the left or top of the split.
+    Client(Xid),
+    /// The workspace, for splits that stay whichever clients are in them.
+    Workspace(usize),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Split {
+    /// A horizontal line, with one side on top of the other.
+    Horizontal,
+    /// A vertical line, with one side next to the other.
+    Vertical,
+}
+
+#[derive(Clone, Copy)]
+pub struct LayoutSplit {
+    pub key: SplitKey,
+    pub split: Split,
+    /// The region that was split in two.
+    pub region: Region,
+    pub ratio: f32,
+}
+
+impl LayoutSplit {
+    /// Where the line between the two sides is.
+    #[must_use]
+    pub fn position(&self) -> u32 {
+        match self.split {
+            Split::Horizontal => self.region.y + split_size(self.region.h, self.ratio),
+            Split::Vertical => self.region.x + split_size(self.region.w, self.ratio),
+        }
+    }
+}
+
+fn split_size(size: u32, ratio: f32) -> u32 {
+    ((size as f32) * ratio) as u32
+}
+
+/// Splits a region using the stored ratio for `key` if there is one,
+/// and records the split so it can be resized later.
+fn split_region(
+    region: &Region,
+    split: Split,
+    key: SplitKey,
+    default_ratio: f32,
+    splits: &mut Vec<LayoutSplit>,
+) -> (Region, Region) {
+    let ratio = SPLIT_RATIOS
+        .read()
+        .expect("SPLIT_RATIOS RwLock poisoned")
+        .get(&key)
+        .copied()
+        .unwrap_or(default_ratio);
+
+    let (first, second) = match split {
+        Split::Horizontal => region.split_at_height(split_size(region.h, ratio)),
+        Split::Vertical => region.split_at_width(split_size(region.w, ratio)),
+    }
+    .expect("Given ratio was invalid");
+
+    splits.push(LayoutSplit {
+        key,
+        split,
+        region: *region,
+        ratio,
+    });
+
+    (first, second)
+}
+
+/// Remembers where each client and split on this monitor was placed,
+/// keeping the ones on other monitors.
+fn store_layout(
+    monitor_region: &Region,
+    resize_actions: &[ResizeAction],
+    splits: Vec<LayoutSplit>,
+) {
+    let mut client_regions = CLIENT_REGIONS
+        .write()
+        .expect("CLIENT_REGIONS RwLock poisoned");
+
+    client_regions.retain(|_id, region| !center_within(region, monitor_region));
+
+    client_regions.extend(
+        resize_actions
+            .iter()
+            .filter_map(|(id, region)| region.map(|region| (*id, region))),
+    );
+
+    let mut layout_splits = LAYOUT_SPLITS
+        .write()
+        .expect("LAYOUT_SPLITS RwLock poisoned");
+
+    layout_splits.retain(|split| !center_within(&split.region, monitor_region));
+
+    layout_splits.extend(splits);
+}
 
 fn main_plus_side(
     clients: &[&Client],
@@ -13,32 +132,46 @@ fn main_plus_side(
     _in_main: u32,
     ratio: f32,
 ) -> Vec<ResizeAction> {
+    let mut splits = vec![];
+
     let resize_actions = match clients.len() {
         0 => vec![],
-        1 => return vec![(clients[0].id(), Some(*monitor_region))],
+        1 => vec![(clients[0].id(), Some(*monitor_region))],
         n => {
-            let (main, stack) = monitor_region
-                .split_at_width(((monitor_region.w as f32) * ratio) as u32)
-                .expect("Given ration was invalid");
-
-            let stack_rows = stack.as_rows(n as u32 - 1);
-
-            vec![main]
-                .into_iter()
-                .chain(stack_rows)
-                .zip(clients)
-                .map(|(r, c)| (c.id(), Some(r)))
-                .collect()
+            let (main, mut stack) = split_region(
+                monitor_region,
+                Split::Vertical,
+                ("main+side", SplitOwner::Workspace(clients[0].workspace())),
+                ratio,
+                &mut splits,
+            );
+
+            let mut resize_actions = vec![(clients[0].id(), Some(main))];
+
+            // Each row is split off the rest of the stack,
+            // so that they can be resized separately.
+            for (i, client) in clients[1..n - 1].iter().enumerate() {
+                let remaining = n - 1 - i;
+
+                let (row, rest) = split_region(
+                    &stack,
+                    Split::Horizontal,
+                    ("main+side", SplitOwner::Client(client.id())),
+                    1. / remaining as f32,
+                    &mut splits,
+                );
+
+                resize_actions.push((client.id(), Some(row)));
+                stack = rest;
+            }
+
+            resize_actions.push((clients[n - 1].id(), Some(stack)));
+
+            resize_actions
         }
     };
 
-    *CLIENT_REGIONS
-        .write()
-        .expect("CLIENT_REGIONS RwLock poisoned") = resize_actions
-        .clone()
-        .into_iter()
-        .map(|(id, region)| (id, region.expect("Region was None")))
-        .collect::<HashMap<_, _>>();
+    store_layout(monitor_region, &resize_actions, splits);
 
     resize_actions
 }
@@ -50,16 +183,7 @@ fn dwindle(
     _in_main: u32,
     _ratio: f32,
 ) -> Vec<ResizeAction> {
-    enum Split {
-        Horizontal,
-        Vertical,
-    }
-
-    if clients.is_empty() {
-        return vec![];
-    }
-
-    let mut clients = clients.to_vec();
+    let mut splits = vec![];
 
     let mut split = if monitor_region.w > monitor_region.h {
         Split::Vertical
@@ -67,46 +191,76 @@ fn dwindle(
         Split::Horizontal
     };
 
-    let mut actions = vec![(clients.remove(0).id(), *monitor_region)];
+    // With no clients this stores nothing, clearing what was stored for this monitor.
+    let mut actions = clients
+        .first()
+        .map(|client| vec![(client.id(), *monitor_region)])
+        .unwrap_or_default();
 
-    for client in clients {
-        match split {
-            Split::Horizontal => {
-                let last_client = actions.last_mut().unwrap_or_else(|| unreachable!());
+    for client in clients.iter().skip(1) {
+        let last_client = actions.last_mut().unwrap_or_else(|| unreachable!());
 
-                let (top, bottom) = last_client
-                    .1
-                    .split_at_height(last_client.1.h / 2)
-                    .expect("Invalid region");
+        let (first, second) = split_region(
+            &last_client.1,
+            split,
+            ("dwindle", SplitOwner::Client(last_client.0)),
+            0.5,
+            &mut splits,
+        );
 
-                last_client.1 = top;
+        last_client.1 = first;
 
-                actions.push((client.id(), bottom));
+        actions.push((client.id(), second));
 
-                split = Split::Vertical;
-            }
-            Split::Vertical => {
-                let last_client = actions.last_mut().unwrap_or_else(|| unreachable!());
+        split = match split {
+            Split::Horizontal => Split::Vertical,
+            Split::Vertical => Split::Horizontal,
+        };
+    }
 
-                let (left, right) = last_client
-                    .1
-                    .split_at_width(last_client.1.w / 2)
-                    .expect("Invalid region");
+    let resize_actions = actions
+        .into_iter()
+        .map(|(id, region)| (id, Some(region)))
+        .collect::<Vec<_>>();
 
-                last_client.1 = left;
+    store_layout(monitor_region, &resize_actions, splits);
 
-                actions.push((client.id(), right));
+    resize_actions
+}
 
-                split = Split::Horizontal;
-            }
-        }
+/// Forgets the splits belonging to a client.
+fn forget_client_splits(id: Xid) -> penrose::Result<()> {
+    let owned_by_client = |key: &SplitKey| key.1 == SplitOwner::Client(id);
+
+    SPLIT_RATIOS
+        .write()
+        .map_err(|_| PenroseError::Raw("SPLIT_RATIOS RwLock poisoned".into()))?
+        .retain(|key, _ratio| !owned_by_client(key));
+
+    LAYOUT_SPLITS
+        .write()
+        .map_err(|_| PenroseError::Raw("LAYOUT_SPLITS RwLock poisoned".into()))?
+        .retain(|split| !owned_by_client(&split.key));
+
+    Ok(())
+}
+
+/// Forgets the splits of clients when they close.
+pub struct LayoutSplitsHook {}
+
+impl LayoutSplitsHook {
+    #[must_use]
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
     }
+}
 
-    actions
-        .into_iter()
-        .map(|(id, region)| (id, Some(region)))
-        .collect()
+impl<X: XConn> Hook<X> for LayoutSplitsHook {
+    fn remove_client(&mut self, _wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        forget_client_splits(id)
+    }
 }
+
 #[must_use]
 pub fn layouts() -> Vec<Layout> {
     vec![
@@ -114,3 +268,73 @@ pub fn layouts() -> Vec<Layout> {
         Layout::new("dwindle", LayoutConf::default(), dwindle, 0, 0.),
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(split: Split, ratio: f32) -> LayoutSplit {
+        LayoutSplit {
+            key: ("test", SplitOwner::Client(0)),
+            split,
+            region: Region::new(100, 50, 400, 200),
+            ratio,
+        }
+    }
+
+    #[test]
+    fn position_is_on_the_split_axis() {
+        assert_eq!(split(Split::Vertical, 0.5).position(), 300);
+        assert_eq!(split(Split::Vertical, 0.25).position(), 200);
+        assert_eq!(split(Split::Horizontal, 0.5).position(), 150);
+        assert_eq!(split(Split::Horizontal, 0.8).position(), 210);
+    }
+
+    #[test]
+    fn split_region_uses_the_default_ratio() {
+        let region = Region::new(0, 0, 400, 200);
+        let mut splits = vec![];
+
+        let (first, second) = split_region(
+            &region,
+            Split::Vertical,
+            ("test", SplitOwner::Client(1)),
+            0.25,
+            &mut splits,
+        );
+
+        assert_eq!(first, Region::new(0, 0, 100, 200));
+        assert_eq!(second, Region::new(100, 0, 300, 200));
+        assert_eq!(splits.len(), 1);
+        assert_eq!(splits[0].position(), 100);
+    }
+
+    #[test]
+    fn split_region_uses_stored_ratios() {
+        let key = ("test", SplitOwner::Client(2));
+        SPLIT_RATIOS.write().unwrap().insert(key, 0.75);
+
+        let region = Region::new(0, 0, 400, 200);
+        let mut splits = vec![];
+
+        let (first, second) = split_region(&region, Split::Horizontal, key, 0.5, &mut splits);
+
+        assert_eq!(first, Region::new(0, 0, 400, 150));
+        assert_eq!(second, Region::new(0, 150, 400, 50));
+        assert!((splits[0].ratio - 0.75).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn closed_clients_splits_are_forgotten() {
+        let closed = ("test", SplitOwner::Client(3));
+        let kept = ("test", SplitOwner::Workspace(3));
+        SPLIT_RATIOS.write().unwrap().insert(closed, 0.3);
+        SPLIT_RATIOS.write().unwrap().insert(kept, 0.3);
+
+        forget_client_splits(3).unwrap();
+
+        let ratios = SPLIT_RATIOS.read().unwrap();
+        assert!(!ratios.contains_key(&closed));
+        assert!(ratios.contains_key(&kept));
+    }
+}
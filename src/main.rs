@@ -24,6 +24,8 @@
 
 mod key_bindings;
 pub use key_bindings::*;
+mod mouse_bindings;
+pub use mouse_bindings::*;
 mod new_window_hook;
 pub use new_window_hook::*;
 mod setup;
@@ -38,10 +40,47 @@ mod x_data;
 pub use x_data::*;
 mod window_switcher;
 pub use window_switcher::*;
+mod geometry;
+pub use geometry::*;
+mod floating;
+pub use floating::*;
+mod focus_history;
+pub use focus_history::*;
+mod picker;
+pub use picker::*;
+mod run_or_raise;
+pub use run_or_raise::*;
+mod scratchpads;
+pub use scratchpads::*;
+mod fullscreen;
+pub use fullscreen::*;
+mod sticky;
+pub use sticky::*;
+mod hidden;
+pub use hidden::*;
+mod ewmh;
+pub use ewmh::*;
+mod status;
+pub use status::*;
+mod urgency;
+pub use urgency::*;
+mod workspaces;
+pub use workspaces::*;
+mod overlay;
+pub use overlay::*;
+mod expose;
+pub use expose::*;
+mod hints;
+pub use hints::*;
+mod marks;
+pub use marks::*;
+mod swallow;
+pub use swallow::*;
+mod rules;
+pub use rules::*;
 pub mod layouts;
 
 use penrose::{
-    contrib::{extensions::Scratchpad, hooks::LayoutSymbolAsRootName},
     core::{
         config::Config, helpers::spawn, hooks::HooksVec, manager::WindowManager, ring::Direction,
     },
@@ -50,7 +89,8 @@ use penrose::{
     Selector,
 };
 
-use std::collections::HashMap;
+/// Whether the focus goes with a window when it's sent to another screen.
+const FOCUS_FOLLOWS_MOVED_CLIENT: bool = true;
 
 #[allow(clippy::too_many_lines)]
 fn main() -> penrose::Result<()> {
@@ -71,12 +111,69 @@ fn main() -> penrose::Result<()> {
     }
     .validate()?;
 
-    let scratch_pad = Scratchpad::new("mousepad", 0.8, 0.8);
+    let gap_px = config.gap_px;
+    let (focused_border, unfocused_border) = (config.focused_border, config.unfocused_border);
+
+    let scratchpads = ScratchpadManager::new([
+        NamedScratchpad::new(
+            "notes",
+            "mousepad",
+            ClientMatcher::class("mousepad"),
+            "meta slash",
+        ),
+        NamedScratchpad::new(
+            "terminal",
+            "kitty --class scratch-terminal",
+            ClientMatcher::class("scratch-terminal"),
+            "meta return",
+        )
+        .size(0.6, 0.4)
+        .at(0.5, 0.),
+        NamedScratchpad::new(
+            "music",
+            "spotify",
+            ClientMatcher::class("spotify"),
+            "meta shift P",
+        ),
+        NamedScratchpad::new(
+            "calculator",
+            "gnome-calculator",
+            ClientMatcher::class("gnome-calculator"),
+            "meta equal",
+        )
+        .size(0.2, 0.4),
+    ]);
+
+    let pickers = PickerQueue::new(Rofi);
+
+    // Workspaces can be kept on a monitor with `.pin(index, "DP-1")`.
+    let workspaces = WorkspaceManager::new(WorkspaceFocusPolicy::Warp);
 
     let hooks: HooksVec<_> = vec![
-        LayoutSymbolAsRootName::new(),
-        scratch_pad.get_hook(),
-        NewWindowHook::new(),
+        StatusHook::new(),
+        scratchpads.get_hook(),
+        NewWindowHook::new(
+            vec![
+                PlacementPolicy::Rules,
+                PlacementPolicy::Parent,
+                PlacementPolicy::PointerScreen,
+                PlacementPolicy::FocusedScreen,
+            ],
+            load_rules(),
+        ),
+        SwallowHook::new(["kitty"], ["Xephyr", "xev"]),
+        FocusHistoryHook::new(),
+        layouts::LayoutSplitsHook::new(),
+        pickers.get_hook(),
+        workspaces.get_hook(),
+        RootScrollHook::new(),
+        FullscreenHook::new(),
+        HiddenHook::new(),
+        UrgencyHook::new(focused_border, unfocused_border),
+        MarksHook::new(),
+        EwmhHook::new(ActivationPolicy::MarkUrgent),
+        // After anything that can change workspace.
+        StickyHook::new(),
     ];
 
     let mut keys = BetterKeyBindings::new();
@@ -84,12 +181,37 @@ fn main() -> penrose::Result<()> {
     // Program runners
     keys.add("meta T", |_wm| spawn("kitty"));
     keys.add("meta E", |_wm| spawn("thunar"));
-    keys.add("meta B", |_wm| spawn("vivaldi-stable"));
+    keys.add("meta B", |wm| {
+        run_or_raise(
+            wm,
+            &ClientMatcher::class("vivaldi-stable"),
+            "vivaldi-stable",
+        )
+    });
     keys.add("meta shift B", |_wm| spawn("vivaldi-stable --incognito"));
 
     // Other runners
     keys.add("meta space", |_wm| spawn("rofi -modi drun -show drun"));
-    keys.add("meta slash", move |wm| (scratch_pad.toggle())(wm));
+    let switcher_pickers = pickers.clone();
+    keys.add("meta W", move |wm| {
+        window_switcher(wm, &switcher_pickers);
+        Ok(())
+    });
+    scratchpads.bind(&mut keys);
+    let adopt_scratchpads = scratchpads.clone();
+    let adopt_pickers = pickers.clone();
+    keys.add("meta ctrl S", move |_wm| {
+        adopt_scratchpads.adopt_focused_picker(&adopt_pickers);
+        Ok(())
+    });
+    keys.add("meta O", move |wm| expose(wm, gap_px));
+    keys.add("meta semicolon", |wm| hint_windows(wm, HintAction::Focus));
+    keys.add("meta shift semicolon", |wm| {
+        hint_windows(wm, HintAction::Swap)
+    });
+    keys.add("meta ctrl semicolon", |wm| {
+        hint_windows(wm, HintAction::Close)
+    });
 
     // Penrose commands
     keys.add("meta ctrl escape", |wm| wm.exit());
@@ -107,6 +229,31 @@ fn main() -> penrose::Result<()> {
 
     // Client management
     keys.add("meta Q", |wm| wm.kill_client());
+    keys.add("meta F", |wm| toggle_fullscreen(wm));
+    keys.add("meta S", |wm| toggle_sticky(wm));
+    keys.add("meta U", |wm| focus_urgent(wm));
+    keys.add("meta minus", |wm| hide_focused(wm));
+    let restore_pickers = pickers.clone();
+    keys.add("meta shift minus", move |wm| {
+        restore_hidden_picker(wm, &restore_pickers);
+        Ok(())
+    });
+    keys.add("meta I", move |wm| {
+        picture_in_picture(wm, Corner::BottomRight, gap_px)
+    });
+
+    // Marks
+    keys.add("meta M", |wm| mark_focused(wm));
+    keys.add("meta apostrophe", |wm| jump_to_mark(wm));
+    keys.add("meta shift apostrophe", |wm| swap_with_mark(wm));
+    keys.add("meta ctrl apostrophe", |wm| move_to_mark_workspace(wm));
+
+    // Focus history
+    keys.add("meta grave", |wm| focus_previous_window(wm));
+    keys.add("alt tab", |wm| cycle_focus_history(wm, Direction::Forward));
+    keys.add("alt shift tab", |wm| {
+        cycle_focus_history(wm, Direction::Backward)
+    });
 
     // Stuff in all 4 directions
     for (key_options, direction) in [
@@ -120,22 +267,78 @@ fn main() -> penrose::Result<()> {
             keys.add(format!("meta {key}"), move |wm| {
                 wm.switch_focus_in_direction(direction)
             });
+
+            // Moving clients around
+            keys.add(format!("meta shift {key}"), move |wm| {
+                wm.move_in_direction(direction)
+            });
+
+            // Resizing clients
+            keys.add(format!("meta alt {key}"), move |wm| {
+                wm.grow_in_direction(direction)
+            });
+
+            // Shrinking floating clients
+            keys.add(format!("meta alt shift {key}"), move |wm| {
+                match wm.focused_client_id() {
+                    Some(id) if !is_tiled(wm, id)? => shrink_floating(wm, id, direction),
+                    _ => Ok(()),
+                }
+            });
+
+            // Sending clients to screens
+            keys.add(format!("meta ctrl shift {key}"), move |wm| {
+                wm.send_client_in_direction(direction, FOCUS_FOLLOWS_MOVED_CLIENT)
+            });
         }
     }
 
     keys.add("meta tab", |wm| wm.drag_client(Direction::Forward));
 
+    // Floating clients
+    keys.add("meta shift space", |wm| toggle_floating(wm));
+    keys.add("meta alt C", |wm| center_floating(wm));
+    keys.add("meta alt S", |wm| cycle_floating_size(wm));
+    for (key, snap) in [
+        ("H", Snap::Left),
+        ("L", Snap::Right),
+        ("K", Snap::Top),
+        ("J", Snap::Bottom),
+        ("Y", Snap::TopLeft),
+        ("U", Snap::TopRight),
+        ("B", Snap::BottomLeft),
+        ("N", Snap::BottomRight),
+    ] {
+        keys.add(format!("meta ctrl alt {key}"), move |wm| {
+            snap_floating(wm, snap)
+        });
+    }
+
+    // Cycle the client through the screens from left to right
+    keys.add("meta shift period", |wm| {
+        wm.cycle_client_to_screen(Direction::Forward, FOCUS_FOLLOWS_MOVED_CLIENT)
+    });
+    keys.add("meta shift comma", |wm| {
+        wm.cycle_client_to_screen(Direction::Backward, FOCUS_FOLLOWS_MOVED_CLIENT)
+    });
+
+    // Swap the workspaces on the focused screen and the next one
+    for (key, direction) in [
+        ("period", Direction::Forward),
+        ("comma", Direction::Backward),
+    ] {
+        let swap_workspaces = workspaces.clone();
+        keys.add(format!("meta alt {key}"), move |wm| {
+            swap_workspaces.swap_visible_workspaces(wm, direction)
+        });
+    }
+
     // Stuff in only 2 directions
     for (key_options, direction) in [
         (["H", "left"], Direction::Backward),
         (["L", "right"], Direction::Forward),
     ] {
         for key in key_options {
-            // Move client to screen
-            keys.add(format!("meta shift {key}"), move |wm| {
-                wm.cycle_client_to_screen(direction)
-            });
-
             // Move to workspace
             keys.add(format!("meta ctrl {key}"), move |wm| {
                 wm.cycle_workspace(direction)
@@ -143,12 +346,12 @@ fn main() -> penrose::Result<()> {
         }
     }
 
-    // Workspace management
+    // Workspace management, only for the workspaces from the config
     for i in config.ws_range() {
-        // Switch to workspace i
+        // Switch to workspace i, or back to the previous one if it's already shown
+        let focus_workspaces = workspaces.clone();
         keys.add(format!("meta {i}"), move |wm| {
-            wm.focus_workspace(&Selector::Index(i - 1))?;
-            Ok(())
+            focus_workspaces.focus_workspace(wm, i - 1)
         });
 
         // Move client to workspace i
@@ -158,6 +361,33 @@ fn main() -> penrose::Result<()> {
         });
     }
 
+    let previous_workspaces = workspaces.clone();
+    keys.add("meta BackSpace", move |wm| {
+        previous_workspaces.previous_workspace(wm)
+    });
+
+    // Workspaces added at runtime
+    let add_workspaces = workspaces.clone();
+    let add_pickers = pickers.clone();
+    keys.add("meta ctrl N", move |_wm| {
+        add_workspaces.add_workspace_picker(&add_pickers);
+        Ok(())
+    });
+    let rename_workspaces = workspaces.clone();
+    let rename_pickers = pickers.clone();
+    keys.add("meta ctrl R", move |wm| {
+        rename_workspaces.rename_workspace_picker(wm, &rename_pickers);
+        Ok(())
+    });
+    let app_workspaces = workspaces.clone();
+    keys.add("meta ctrl A", move |wm| {
+        app_workspaces.focused_to_app_workspace(wm)
+    });
+    let remove_workspaces = workspaces.clone();
+    keys.add("meta ctrl minus", move |wm| {
+        remove_workspaces.remove_focused_workspace(wm)
+    });
+
     // Used `xev` to find the names for these
 
     // Volume control
@@ -200,14 +430,16 @@ fn main() -> penrose::Result<()> {
     );
     wm.init()?;
 
-    let mouse_bindings = HashMap::new();
+    let mut mouse_bindings = BetterMouseBindings::new();
 
-    // mouse_bindings.insert(
-    //     MouseEventKind::Motion,
-    //     MouseState::new(MouseButton::)
-    // );
+    mouse_bindings.add_drag_move("meta button1");
+    mouse_bindings.add_drag_resize("meta button3");
+    mouse_bindings.add_toggle_floating("meta button1");
 
-    wm.grab_keys_and_run(keys.into_penrose_bindings(), mouse_bindings)?;
+    wm.grab_keys_and_run(
+        keys.into_penrose_bindings(),
+        mouse_bindings.into_penrose_bindings(),
+    )?;
 
     Ok(())
 }
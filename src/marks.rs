@@ -0,0 +1,140 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use lazy_static::lazy_static;
+use penrose::{
+    core::{xconnection::XConn, Hook},
+    PenroseError, Selector, WindowManager, Xid,
+};
+
+use crate::{is_hidden, read_letter, update_root_name, WindowManagerExt};
+
+lazy_static! {
+    /// Each mark and the client it's on. A client can have more than one.
+    static ref MARKS: RwLock<HashMap<char, Xid>> = RwLock::new(HashMap::new());
+}
+
+fn marks_poisoned<T>(_: T) -> PenroseError {
+    PenroseError::Raw("MARKS RwLock poisoned".into())
+}
+
+/// The marks on a client, in alphabetical order.
+#[must_use]
+pub fn marks_of(id: Xid) -> Vec<char> {
+    let mut marks = MARKS
+        .read()
+        .map(|marks| {
+            marks
+                .iter()
+                .filter(|(_mark, marked)| **marked == id)
+                .map(|(mark, _marked)| *mark)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    marks.sort_unstable();
+    marks
+}
+
+/// Reads a letter, then gives the client marked with it.
+fn read_marked_client() -> penrose::Result<Option<Xid>> {
+    let mark = match read_letter() {
+        Some(mark) => mark,
+        None => return Ok(None),
+    };
+
+    Ok(MARKS.read().map_err(marks_poisoned)?.get(&mark).copied())
+}
+
+/// Reads a letter, then marks the focused client with it,
+/// taking the mark off any other client.
+///
+/// # Errors
+/// Errors if the root window name couldn't be updated.
+pub fn mark_focused<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    let id = match wm.focused_client_id() {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    if let Some(mark) = read_letter() {
+        MARKS.write().map_err(marks_poisoned)?.insert(mark, id);
+    }
+
+    update_root_name(wm)
+}
+
+/// Reads a letter, then focuses the client marked with it, wherever it is, unless it is hidden.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn jump_to_mark<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    match read_marked_client()? {
+        Some(id) if !is_hidden(id) => wm.focus_client_anywhere(id),
+        _ => Ok(()),
+    }
+}
+
+/// Reads a letter, then swaps the focused client with the client marked with it.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn swap_with_mark<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    let focused = match wm.focused_client_id() {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    match read_marked_client()? {
+        Some(id) if id != focused => wm.swap_clients(focused, id),
+        _ => Ok(()),
+    }
+}
+
+/// Reads a letter, then moves the focused client to the workspace of the client marked with it
+/// and follows it there.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn move_to_mark_workspace<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    let focused = match wm.focused_client_id() {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    let workspace = match read_marked_client()?
+        .and_then(|id| wm.client(&Selector::WinId(id)))
+        .map(|client| client.workspace())
+    {
+        Some(workspace) => workspace,
+        None => return Ok(()),
+    };
+
+    wm.move_client_to_workspace(focused, workspace)?;
+
+    for screen in 0..wm.n_screens() {
+        wm.layout_screen(screen)?;
+    }
+
+    wm.focus_client_anywhere(focused)
+}
+
+/// Clears the marks on clients when they close.
+pub struct MarksHook {}
+
+impl MarksHook {
+    #[must_use]
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+}
+
+impl<X: XConn> Hook<X> for MarksHook {
+    fn remove_client(&mut self, _wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        MARKS
+            .write()
+            .map_err(marks_poisoned)?
+            .retain(|_mark, marked| *marked != id);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::Rc,
+    sync::mpsc::{self, Receiver},
+};
+
+use penrose::{
+    core::{
+        bindings::{
+            ModifierKey, MouseBindings, MouseButton, MouseEvent, MouseEventHandler, MouseEventKind,
+            MouseState,
+        },
+        data_types::{Point, Region},
+        ring::Direction,
+        xconnection::XConn,
+        Hook,
+    },
+    PenroseError, Selector, WindowManager, Xid,
+};
+
+use crate::{is_tiled, make_floating, make_tiled, raise_window, wake_wm, window_region};
+
+/// The smallest a window can be resized to with the mouse.
+const MIN_DRAG_SIZE: u32 = 100;
+
+/// How far the pointer can move during a press and still count as a click.
+const CLICK_TOLERANCE: u32 = 4;
+
+/// Each binding can have several handlers, run in the order they were added,
+/// so that clicking and dragging can share a button.
+pub struct BetterMouseBindings<X: XConn + 'static> {
+    bindings: HashMap<(MouseEventKind, String), Vec<MouseEventHandler<X>>>,
+}
+
+impl<X: XConn + 'static> Default for BetterMouseBindings<X> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<X: XConn + 'static> BetterMouseBindings<X> {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            bindings: HashMap::new(),
+        }
+    }
+
+    fn button_parse(button_str: &str) -> penrose::Result<MouseState> {
+        let mut parts = button_str.split(' ').collect::<Vec<_>>();
+
+        let button = match parts.pop().unwrap_or_default().to_lowercase().as_str() {
+            "button1" => MouseButton::Left,
+            "button2" => MouseButton::Middle,
+            "button3" => MouseButton::Right,
+            "button4" => MouseButton::ScrollUp,
+            "button5" => MouseButton::ScrollDown,
+            other => return Err(PenroseError::Raw(format!("Unknown mouse button {other:?}"))),
+        };
+
+        let modifiers = parts
+            .into_iter()
+            .filter_map(|modifier| match modifier {
+                "meta" => Some(ModifierKey::Meta),
+                "alt" => Some(ModifierKey::Alt),
+                "shift" => Some(ModifierKey::Shift),
+                "ctrl" => Some(ModifierKey::Ctrl),
+                _ => None,
+            })
+            .collect();
+
+        Ok(MouseState::new(button, modifiers))
+    }
+
+    /// Binds a mouse button, written like "meta button1", to a handler.
+    pub fn add(
+        &mut self,
+        button: impl Into<String>,
+        kind: MouseEventKind,
+        func: impl FnMut(&mut WindowManager<X>, &MouseEvent) -> penrose::Result<()> + 'static,
+    ) {
+        self.bindings
+            .entry((kind, button.into()))
+            .or_default()
+            .push(Box::new(func));
+    }
+
+    /// Dragging with this button moves floating windows.
+    pub fn add_drag_move(&mut self, button: impl Into<String>) {
+        self.add_drag(button.into(), |start, dx, dy| {
+            Region::new(
+                (start.x as i32 + dx).max(0) as u32,
+                (start.y as i32 + dy).max(0) as u32,
+                start.w,
+                start.h,
+            )
+        });
+    }
+
+    /// Dragging with this button resizes floating windows from their bottom right corner.
+    pub fn add_drag_resize(&mut self, button: impl Into<String>) {
+        self.add_drag(button.into(), |start, dx, dy| {
+            Region::new(
+                start.x,
+                start.y,
+                (start.w as i32 + dx).max(MIN_DRAG_SIZE as i32) as u32,
+                (start.h as i32 + dy).max(MIN_DRAG_SIZE as i32) as u32,
+            )
+        });
+    }
+
+    /// Clicking with this button switches a window between floating and tiled.
+    ///
+    /// Only presses released without moving count,
+    /// so this can share a button with dragging.
+    pub fn add_toggle_floating(&mut self, button: impl Into<String>) {
+        let button = button.into();
+        let pressed = Rc::new(RefCell::new(None::<(Xid, Point)>));
+
+        let press_pressed = pressed.clone();
+        self.add(button.clone(), MouseEventKind::Press, move |_wm, event| {
+            press_pressed.replace(Some((event.id, event.rpt)));
+            Ok(())
+        });
+
+        self.add(button, MouseEventKind::Release, move |wm, event| {
+            let clicked = match pressed.replace(None) {
+                Some((id, point)) if is_click(point, event.rpt) => id,
+                _ => return Ok(()),
+            };
+
+            if wm.client(&Selector::WinId(clicked)).is_none() {
+                return Ok(());
+            }
+
+            if is_tiled(wm, clicked)? {
+                make_floating(wm, clicked)
+            } else {
+                make_tiled(wm, clicked)
+            }
+        });
+    }
+
+    /// Registers the press, motion and release of a drag,
+    /// with `f` giving the new region from the starting one and how far the pointer has moved.
+    fn add_drag(&mut self, button: String, f: fn(Region, i32, i32) -> Region) {
+        let drag = Rc::new(RefCell::new(None::<Drag>));
+
+        let press_drag = drag.clone();
+        self.add(button.clone(), MouseEventKind::Press, move |wm, event| {
+            // Tiled windows are placed by the layout, so leave them alone.
+            if wm.client(&Selector::WinId(event.id)).is_none() || is_tiled(wm, event.id)? {
+                return Ok(());
+            }
+
+            if let Some(region) = window_region(event.id) {
+                raise_window(event.id);
+                press_drag.replace(Some(Drag {
+                    id: event.id,
+                    pointer: event.rpt,
+                    region,
+                }));
+            }
+
+            Ok(())
+        });
+
+        let motion_drag = drag.clone();
+        self.add(button.clone(), MouseEventKind::Motion, move |wm, event| {
+            if let Some(drag) = *motion_drag.borrow() {
+                let dx = event.rpt.x as i32 - drag.pointer.x as i32;
+                let dy = event.rpt.y as i32 - drag.pointer.y as i32;
+
+                wm.position_client(drag.id, f(drag.region, dx, dy), true)?;
+            }
+
+            Ok(())
+        });
+
+        self.add(button, MouseEventKind::Release, move |_wm, _event| {
+            drag.replace(None);
+            Ok(())
+        });
+    }
+
+    #[must_use]
+    pub fn into_penrose_bindings(self) -> MouseBindings<X> {
+        self.bindings
+            .into_iter()
+            .filter_map(|((kind, button_str), mut funcs)| {
+                let state = match Self::button_parse(&button_str) {
+                    Ok(state) => state,
+                    Err(err) => {
+                        tracing::error!("Skipping mouse binding {:?}: {}", button_str, err);
+                        return None;
+                    }
+                };
+
+                let penrose_fn: MouseEventHandler<X> =
+                    Box::new(move |wm: &mut WindowManager<X>, event: &MouseEvent| {
+                        for func in &mut funcs {
+                            // I don't care if this fails, the show must go on
+                            let _ = func(wm, event);
+                        }
+                        Ok(())
+                    });
+
+                Some(((kind, state), penrose_fn))
+            })
+            .collect::<HashMap<_, _>>()
+    }
+}
+
+/// Whether the pointer stayed close enough to where it was pressed.
+const fn is_click(pressed: Point, released: Point) -> bool {
+    pressed.x.abs_diff(released.x) <= CLICK_TOLERANCE
+        && pressed.y.abs_diff(released.y) <= CLICK_TOLERANCE
+}
+
+#[derive(Clone, Copy)]
+struct Drag {
+    id: Xid,
+    /// Where the pointer was when the drag started.
+    pointer: Point,
+    /// Where the window was when the drag started.
+    region: Region,
+}
+
+/// Cycles workspaces when scrolling over the desktop.
+///
+/// Penrose can only grab buttons for every window,
+/// which would stop scrolling working in them,
+/// so this listens for scrolls on the root window on its own connection instead.
+///
+/// X only lets one client select button presses on a window,
+/// so this doesn't work if something else, like a desktop, already has them on the root window.
+pub struct RootScrollHook {
+    scrolls: Receiver<Direction>,
+}
+
+impl RootScrollHook {
+    #[must_use]
+    pub fn new() -> Box<Self> {
+        let (sender, scrolls) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let (conn, screen_num) = match xcb::Connection::connect(None) {
+                Ok(connection) => connection,
+                Err(err) => {
+                    tracing::error!("Failed to connect to X for root scrolling: {}", err);
+                    return;
+                }
+            };
+
+            let root = match conn.get_setup().roots().nth(screen_num.max(0) as usize) {
+                Some(screen) => screen.root(),
+                None => return,
+            };
+
+            if let Err(err) = conn.send_and_check_request(&xcb::x::ChangeWindowAttributes {
+                window: root,
+                value_list: &[xcb::x::Cw::EventMask(xcb::x::EventMask::BUTTON_PRESS)],
+            }) {
+                tracing::error!(
+                    "Failed to listen for scrolling on the root window, \
+                     so scrolling won't switch workspaces. \
+                     Another client is probably already listening for button presses on it: {}",
+                    err
+                );
+                return;
+            }
+
+            while let Ok(event) = conn.wait_for_event() {
+                let direction = match event {
+                    xcb::Event::X(xcb::x::Event::ButtonPress(press)) => match press.detail() {
+                        4 => Direction::Backward,
+                        5 => Direction::Forward,
+                        _ => continue,
+                    },
+                    _ => continue,
+                };
+
+                if sender.send(direction).is_err() {
+                    return;
+                }
+
+                wake_wm();
+            }
+        });
+
+        Box::new(Self { scrolls })
+    }
+}
+
+impl<X: XConn> Hook<X> for RootScrollHook {
+    fn event_handled(&mut self, wm: &mut WindowManager<X>) -> penrose::Result<()> {
+        while let Ok(direction) = self.scrolls.try_recv() {
+            wm.cycle_workspace(direction)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use penrose::xcb::XcbConnection;
+
+    use super::*;
+
+    type Bindings = BetterMouseBindings<XcbConnection>;
+
+    #[test]
+    fn unknown_buttons_are_errors() {
+        assert!(Bindings::button_parse("meta button1").is_ok());
+        assert!(Bindings::button_parse("meta button9").is_err());
+        assert!(Bindings::button_parse("").is_err());
+    }
+
+    #[test]
+    fn small_movements_are_still_clicks() {
+        assert!(is_click(Point::new(10, 10), Point::new(12, 7)));
+        assert!(!is_click(Point::new(10, 10), Point::new(30, 10)));
+    }
+}
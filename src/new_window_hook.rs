@@ -1,57 +1,260 @@
+use std::collections::{HashMap, HashSet};
+
 use penrose::{
-    core::{data_types::Point, xconnection::XConn, Hook},
-    Selector,
+    core::{data_types::Region, xconnection::XConn, Hook},
+    Selector, WindowManager, Xid,
+};
+
+use crate::{
+    configure_window, find_rule, has_window_type, pointer_position, raise_window, set_fullscreen,
+    set_sticky, transient_for, window_region, WindowRule,
 };
 
-use crate::X_DATA;
-
-fn mouse_position() -> Point {
-    // The xcb library is quite weird, so rust analyzer cannot infer the type of the returned value.
-    let cookie: xcb::x::QueryPointerCookie = X_DATA.conn.send_request(&xcb::x::QueryPointer {
-        window: X_DATA.root,
-    });
-
-    let reply: xcb::x::QueryPointerReply = X_DATA.conn.wait_for_reply(cookie).unwrap();
-
-    Point::new(
-        reply
-            .root_x()
-            .try_into()
-            .expect("Mouse position was negative"),
-        reply
-            .root_y()
-            .try_into()
-            .expect("Mouse position was negative"),
-    )
+/// The `_NET_WM_WINDOW_TYPE`s of windows that float on their own.
+const FLOATING_WINDOW_TYPES: [&str; 4] = ["dialog", "utility", "splash", "popup_menu"];
+
+/// Whether a window is a dialog or something like it, which shouldn't be tiled.
+fn is_dialog(id: Xid) -> bool {
+    transient_for(id).is_some()
+        || FLOATING_WINDOW_TYPES
+            .iter()
+            .any(|window_type| has_window_type(id, window_type))
+}
+
+/// The region of the screen showing a workspace, or of the focused screen if none is.
+fn screen_region_for<X: XConn>(wm: &WindowManager<X>, workspace: usize) -> Option<Region> {
+    (0..wm.n_screens())
+        .filter_map(|index| wm.screen(&Selector::Index(index)))
+        .find(|screen| screen.wix == workspace)
+        .or_else(|| wm.screen(&Selector::Index(wm.active_screen_index())))
+        .map(|screen| screen.region(false))
+}
+
+/// A way of picking the workspace a new window goes on.
+pub enum PlacementPolicy {
+    /// The workspace on the screen under the pointer.
+    PointerScreen,
+    /// The workspace on the focused screen.
+    FocusedScreen,
+    /// The workspace of the window it's a dialog for, from `WM_TRANSIENT_FOR`.
+    Parent,
+    /// The workspace or screen from the window rule that matched it.
+    Rules,
 }
 
-pub struct NewWindowHook {}
+impl PlacementPolicy {
+    /// The workspace this policy puts the new window `id` on,
+    /// or `None` if it doesn't apply.
+    fn workspace<X: XConn>(
+        &self,
+        wm: &WindowManager<X>,
+        id: Xid,
+        rule: Option<&WindowRule>,
+    ) -> Option<usize> {
+        match self {
+            Self::PointerScreen => {
+                let pointer = pointer_position()?;
+
+                (0..wm.n_screens())
+                    .filter_map(|index| wm.screen(&Selector::Index(index)))
+                    .find(|screen| screen.contains(pointer))
+                    .map(|screen| screen.wix)
+            }
+            Self::FocusedScreen => wm
+                .screen(&Selector::Index(wm.active_screen_index()))
+                .map(|screen| screen.wix),
+            Self::Parent => wm
+                .client(&Selector::WinId(transient_for(id)?))
+                .map(|parent| parent.workspace()),
+            Self::Rules => rule?.workspace(wm),
+        }
+    }
+}
+
+/// Puts new windows on a workspace picked by the first policy that applies,
+/// or leaves them where penrose put them if none do.
+///
+/// Dialogs are floated in the middle of the window they belong to, or of their screen,
+/// and the first window rule that matches a new window is applied to it.
+pub struct NewWindowHook {
+    policies: Vec<PlacementPolicy>,
+    rules: Vec<WindowRule>,
+    /// New dialogs, waiting to be added to a workspace.
+    dialogs: HashSet<Xid>,
+    /// New windows that matched a rule, waiting to be added to a workspace.
+    matched: HashMap<Xid, usize>,
+    /// Windows with a border width from a rule, which penrose resets when it lays them out.
+    borders: HashMap<Xid, u32>,
+    /// New windows that shouldn't take the focus.
+    no_focus: HashSet<Xid>,
+    last_focused: Option<Xid>,
+}
 
 impl NewWindowHook {
     #[must_use]
-    pub fn new() -> Box<Self> {
-        Box::new(Self {})
+    pub fn new(policies: Vec<PlacementPolicy>, rules: Vec<WindowRule>) -> Box<Self> {
+        Box::new(Self {
+            policies,
+            rules,
+            dialogs: HashSet::new(),
+            matched: HashMap::new(),
+            borders: HashMap::new(),
+            no_focus: HashSet::new(),
+            last_focused: None,
+        })
+    }
+
+    fn apply_border(id: Xid, border: u32) {
+        configure_window(id, &[xcb::x::ConfigWindow::BorderWidth(border)]);
+    }
+
+    /// Centres a dialog on the window it belongs to if it fits, or else on its screen.
+    fn center_dialog<X: XConn>(
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        workspace: usize,
+    ) -> penrose::Result<()> {
+        let region = match window_region(id) {
+            Some(region) => region,
+            None => return Ok(()),
+        };
+
+        let parent = transient_for(id)
+            .filter(|parent| wm.client(&Selector::WinId(*parent)).is_some())
+            .and_then(window_region);
+
+        let centered = parent
+            .and_then(|parent| region.centered_in(&parent).ok())
+            .or_else(|| {
+                screen_region_for(wm, workspace).and_then(|screen| region.centered_in(&screen).ok())
+            });
+
+        if let Some(centered) = centered {
+            wm.position_client(id, centered, true)?;
+            raise_window(id);
+        }
+
+        Ok(())
     }
 }
 
 impl<X: XConn> Hook<X> for NewWindowHook {
-    fn new_client(
+    fn new_client(&mut self, wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        let rule_index = find_rule(&self.rules, wm, id);
+        let rule = rule_index.map(|index| &self.rules[index]);
+
+        let workspace = self
+            .policies
+            .iter()
+            .find_map(|policy| policy.workspace(wm, id, rule))
+            .filter(|workspace| *workspace < wm.all_workspaces(&Selector::Any).len());
+
+        let dialog = is_dialog(id);
+        let floating =
+            dialog || rule.map_or(false, |rule| rule.floating || rule.geometry.is_some());
+
+        // Penrose doesn't keep every window it's told about.
+        if let Some(client) = wm.client_mut(&Selector::WinId(id)) {
+            if let Some(workspace) = workspace {
+                client.set_workspace(workspace);
+            }
+
+            if floating {
+                client.externally_managed();
+            }
+        }
+
+        if dialog {
+            self.dialogs.insert(id);
+        }
+
+        if let Some(index) = rule_index {
+            self.matched.insert(id, index);
+        }
+
+        Ok(())
+    }
+
+    fn client_added_to_workspace(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        workspace_index: usize,
+    ) -> penrose::Result<()> {
+        if self.dialogs.remove(&id) {
+            Self::center_dialog(wm, id, workspace_index)?;
+        }
+
+        let rule = match self.matched.remove(&id) {
+            Some(index) => &self.rules[index],
+            None => return Ok(()),
+        };
+
+        // The rule's geometry wins over centring dialogs.
+        let screen = screen_region_for(wm, workspace_index);
+        if let Some(region) = screen.and_then(|screen| rule.region_on(&screen)) {
+            wm.position_client(id, region, true)?;
+        }
+
+        if rule.fullscreen {
+            set_fullscreen(wm, id, true)?;
+        }
+
+        if rule.sticky {
+            set_sticky(id, true)?;
+        }
+
+        if let Some(border) = rule.border {
+            self.borders.insert(id, border);
+            Self::apply_border(id, border);
+        }
+
+        if rule.no_focus {
+            self.no_focus.insert(id);
+        }
+
+        Ok(())
+    }
+
+    fn focus_change(&mut self, wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        if self.no_focus.remove(&id) {
+            let previous = self
+                .last_focused
+                .filter(|previous| wm.client(&Selector::WinId(*previous)).is_some());
+
+            if let Some(previous) = previous {
+                wm.focus_client(&Selector::WinId(previous))?;
+                return Ok(());
+            }
+        }
+
+        self.last_focused = Some(id);
+
+        Ok(())
+    }
+
+    fn layout_applied(
         &mut self,
-        wm: &mut penrose::WindowManager<X>,
-        id: penrose::Xid,
+        _wm: &mut WindowManager<X>,
+        _workspace_index: usize,
+        _screen_index: usize,
     ) -> penrose::Result<()> {
-        let mouse_position = mouse_position();
+        for (id, border) in &self.borders {
+            Self::apply_border(*id, *border);
+        }
 
-        let current_screen = (0..wm.n_screens())
-            .into_iter()
-            .map(|screen_index| wm.screen(&Selector::Index(screen_index)).unwrap())
-            .find(|screen| screen.contains(mouse_position))
-            .unwrap();
+        Ok(())
+    }
 
-        let current_workspace = current_screen.wix;
+    fn remove_client(&mut self, _wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        self.dialogs.remove(&id);
+        self.matched.remove(&id);
+        self.borders.remove(&id);
+        self.no_focus.remove(&id);
 
-        let client = wm.client_mut(&Selector::WinId(id)).unwrap();
-        client.set_workspace(current_workspace);
+        if self.last_focused == Some(id) {
+            self.last_focused = None;
+        }
 
         Ok(())
     }
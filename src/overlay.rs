@@ -0,0 +1,405 @@
+use std::{collections::HashMap, time::Duration};
+
+use lazy_static::lazy_static;
+use penrose::core::data_types::Point;
+
+use crate::Dracula;
+
+/// The keys used for hint labels, easiest to reach first.
+pub const HINT_KEYS: &str = "asdfghjklqwertyuiopzxcvbnm";
+
+/// The size of a character in the "fixed" font.
+const FONT_WIDTH: u16 = 6;
+const FONT_HEIGHT: u16 = 13;
+const FONT_ASCENT: i16 = 10;
+/// The space around the text in a label.
+const LABEL_PADDING: u16 = 4;
+
+/// How many times to try grabbing the keyboard while the key that started things is still held.
+const GRAB_ATTEMPTS: u32 = 100;
+const GRAB_RETRY_DELAY: Duration = Duration::from_millis(10);
+
+lazy_static! {
+    /// The keycodes of the hint keys and escape, which cancels.
+    static ref HINT_KEY_CODES: HashMap<u8, Option<char>> = {
+        let codes = penrose::core::helpers::keycodes_from_xmodmap()
+            .into_iter()
+            .map(|(string, code)| (string.to_lowercase(), code))
+            .collect::<HashMap<_, _>>();
+
+        HINT_KEYS
+            .chars()
+            .filter_map(|key| codes.get(&key.to_string()).map(|code| (*code, Some(key))))
+            .chain(codes.get("escape").map(|code| (*code, None)))
+            .collect()
+    };
+}
+
+/// Labels made of the hint keys, one for each of `count` things.
+///
+/// Every label is the same length, so none is the start of another.
+#[must_use]
+pub fn hint_labels(count: usize) -> Vec<String> {
+    let keys = HINT_KEYS.chars().collect::<Vec<_>>();
+
+    let mut length = 1;
+    while keys.len().pow(length) < count {
+        length += 1;
+    }
+
+    (0..count)
+        .map(|mut index| {
+            let mut label = vec![' '; length as usize];
+
+            for position in (0..length as usize).rev() {
+                label[position] = keys[index % keys.len()];
+                index /= keys.len();
+            }
+
+            label.into_iter().collect()
+        })
+        .collect()
+}
+
+/// What the user did while an overlay was shown.
+pub enum OverlayInput {
+    /// They typed the label at this index.
+    Label(usize),
+    /// They clicked somewhere that wasn't a label.
+    Click(Point),
+    Cancel,
+}
+
+struct Label {
+    window: xcb::x::Window,
+    /// What has to be typed to pick it.
+    hint: String,
+    /// What is shown, which starts with the hint.
+    text: String,
+}
+
+/// Labels drawn in override-redirect windows above everything else,
+/// while the keyboard and pointer are grabbed.
+///
+/// This has its own connection to X, so that reading input doesn't take events
+/// meant for the window manager off the `X_DATA` connection.
+/// Everything is cleaned up when it's dropped.
+pub struct Overlay {
+    conn: xcb::Connection,
+    root: xcb::x::Window,
+    font: xcb::x::Font,
+    gc: xcb::x::Gcontext,
+    labels: Vec<Label>,
+}
+
+impl Overlay {
+    /// Grabs the keyboard and pointer, returning `None` if they couldn't be grabbed.
+    #[must_use]
+    pub fn new() -> Option<Self> {
+        let (conn, root) = connect()?;
+
+        if !grab_keyboard(&conn, root) {
+            tracing::error!("Failed to grab the keyboard for an overlay");
+            return None;
+        }
+
+        // Clicks are only nice to have, so carry on without them.
+        conn.send_request(&xcb::x::GrabPointer {
+            owner_events: false,
+            grab_window: root,
+            event_mask: xcb::x::EventMask::BUTTON_PRESS,
+            pointer_mode: xcb::x::GrabMode::Async,
+            keyboard_mode: xcb::x::GrabMode::Async,
+            confine_to: xcb::x::WINDOW_NONE,
+            cursor: xcb::x::CURSOR_NONE,
+            time: xcb::x::CURRENT_TIME,
+        });
+
+        let font = conn.generate_id();
+        conn.send_request(&xcb::x::OpenFont {
+            fid: font,
+            name: b"fixed",
+        });
+
+        let gc = conn.generate_id();
+        conn.send_request(&xcb::x::CreateGc {
+            cid: gc,
+            drawable: xcb::x::Drawable::Window(root),
+            // The colours are RGBA, but X wants RGB.
+            value_list: &[
+                xcb::x::Gc::Foreground(Dracula::FG >> 8),
+                xcb::x::Gc::Background(Dracula::SELECTION >> 8),
+                xcb::x::Gc::Font(font),
+            ],
+        });
+
+        let _ = conn.flush();
+
+        Some(Self {
+            conn,
+            root,
+            font,
+            gc,
+            labels: vec![],
+        })
+    }
+
+    /// Shows a label centred on `center` which is picked by typing `hint`,
+    /// with `description` shown after it if it isn't empty.
+    pub fn add_label(&mut self, center: (u32, u32), hint: impl Into<String>, description: &str) {
+        let hint = hint.into();
+        let text = if description.is_empty() {
+            hint.clone()
+        } else {
+            format!("{hint} {description}")
+        };
+
+        let width = text.len() as u16 * FONT_WIDTH + LABEL_PADDING * 2;
+        let height = FONT_HEIGHT + LABEL_PADDING * 2;
+
+        let window = self.conn.generate_id();
+        self.conn.send_request(&xcb::x::CreateWindow {
+            depth: xcb::x::COPY_FROM_PARENT as u8,
+            wid: window,
+            parent: self.root,
+            x: (center.0 as i16).saturating_sub((width / 2) as i16),
+            y: (center.1 as i16).saturating_sub((height / 2) as i16),
+            width,
+            height,
+            border_width: 1,
+            class: xcb::x::WindowClass::InputOutput,
+            visual: xcb::x::COPY_FROM_PARENT,
+            value_list: &[
+                xcb::x::Cw::BackPixel(Dracula::SELECTION >> 8),
+                xcb::x::Cw::BorderPixel(Dracula::PURPLE >> 8),
+                xcb::x::Cw::OverrideRedirect(true),
+                xcb::x::Cw::EventMask(xcb::x::EventMask::EXPOSURE),
+            ],
+        });
+        self.conn.send_request(&xcb::x::MapWindow { window });
+
+        let _ = self.conn.flush();
+
+        self.labels.push(Label { window, hint, text });
+    }
+
+    fn draw(&self, label: &Label) {
+        self.conn.send_request(&xcb::x::ImageText8 {
+            drawable: xcb::x::Drawable::Window(label.window),
+            gc: self.gc,
+            x: LABEL_PADDING as i16,
+            y: LABEL_PADDING as i16 + FONT_ASCENT,
+            string: label.text.as_bytes(),
+        });
+
+        let _ = self.conn.flush();
+    }
+
+    /// Only shows the labels that start with what has been typed so far.
+    fn filter_labels(&self, typed: &str) {
+        for label in &self.labels {
+            if label.hint.starts_with(typed) {
+                self.conn.send_request(&xcb::x::MapWindow {
+                    window: label.window,
+                });
+            } else {
+                self.conn.send_request(&xcb::x::UnmapWindow {
+                    window: label.window,
+                });
+            }
+        }
+
+        let _ = self.conn.flush();
+    }
+
+    /// Waits until a label is typed, something is clicked or escape is pressed.
+    ///
+    /// This blocks the window manager, which handles anything that happened meanwhile afterwards.
+    #[must_use]
+    pub fn read_input(&self) -> OverlayInput {
+        let mut typed = String::new();
+
+        while let Ok(event) = self.conn.wait_for_event() {
+            match event {
+                xcb::Event::X(xcb::x::Event::Expose(expose)) => {
+                    if let Some(label) = self
+                        .labels
+                        .iter()
+                        .find(|label| label.window == expose.window())
+                    {
+                        self.draw(label);
+                    }
+                }
+                xcb::Event::X(xcb::x::Event::KeyPress(press)) => {
+                    let key = match HINT_KEY_CODES.get(&press.detail()) {
+                        Some(Some(key)) => *key,
+                        Some(None) => return OverlayInput::Cancel,
+                        None => continue,
+                    };
+
+                    typed.push(key);
+
+                    if let Some(index) = self.labels.iter().position(|label| label.hint == typed) {
+                        return OverlayInput::Label(index);
+                    }
+
+                    if !self
+                        .labels
+                        .iter()
+                        .any(|label| label.hint.starts_with(&typed))
+                    {
+                        typed.clear();
+                    }
+
+                    self.filter_labels(&typed);
+                }
+                xcb::Event::X(xcb::x::Event::ButtonPress(press)) => {
+                    return OverlayInput::Click(Point::new(
+                        press.root_x().max(0) as u32,
+                        press.root_y().max(0) as u32,
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        OverlayInput::Cancel
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        for label in &self.labels {
+            self.conn.send_request(&xcb::x::DestroyWindow {
+                window: label.window,
+            });
+        }
+
+        self.conn.send_request(&xcb::x::FreeGc { gc: self.gc });
+        self.conn
+            .send_request(&xcb::x::CloseFont { font: self.font });
+        self.conn.send_request(&xcb::x::UngrabKeyboard {
+            time: xcb::x::CURRENT_TIME,
+        });
+        self.conn.send_request(&xcb::x::UngrabPointer {
+            time: xcb::x::CURRENT_TIME,
+        });
+
+        let _ = self.conn.flush();
+    }
+}
+
+/// Waits for a letter to be typed, without showing anything.
+///
+/// Returns `None` if escape was pressed or the keyboard couldn't be grabbed.
+/// Like `Overlay::read_input`, this blocks the window manager.
+#[must_use]
+pub fn read_letter() -> Option<char> {
+    let (conn, root) = connect()?;
+
+    if !grab_keyboard(&conn, root) {
+        tracing::error!("Failed to grab the keyboard to read a letter");
+        return None;
+    }
+
+    let mut letter = None;
+
+    while let Ok(event) = conn.wait_for_event() {
+        if let xcb::Event::X(xcb::x::Event::KeyPress(press)) = event {
+            match HINT_KEY_CODES.get(&press.detail()) {
+                Some(key) => {
+                    letter = *key;
+                    break;
+                }
+                None => continue,
+            }
+        }
+    }
+
+    conn.send_request(&xcb::x::UngrabKeyboard {
+        time: xcb::x::CURRENT_TIME,
+    });
+
+    let _ = conn.flush();
+
+    letter
+}
+
+/// A new connection to X and its root window, for reading input
+/// without taking events off the `X_DATA` connection.
+fn connect() -> Option<(xcb::Connection, xcb::x::Window)> {
+    let (conn, screen_num) = match xcb::Connection::connect(None) {
+        Ok(connection) => connection,
+        Err(err) => {
+            tracing::error!("Failed to connect to X for reading input: {}", err);
+            return None;
+        }
+    };
+
+    let root = conn
+        .get_setup()
+        .roots()
+        .nth(screen_num.max(0) as usize)?
+        .root();
+
+    Some((conn, root))
+}
+
+/// Grabs the keyboard on `conn`.
+///
+/// Penrose holds the keyboard until the key that ran the binding is released,
+/// so this keeps trying for a while.
+fn grab_keyboard(conn: &xcb::Connection, root: xcb::x::Window) -> bool {
+    for _ in 0..GRAB_ATTEMPTS {
+        let cookie = conn.send_request(&xcb::x::GrabKeyboard {
+            owner_events: false,
+            grab_window: root,
+            time: xcb::x::CURRENT_TIME,
+            pointer_mode: xcb::x::GrabMode::Async,
+            keyboard_mode: xcb::x::GrabMode::Async,
+        });
+
+        if let Ok(reply) = conn.wait_for_reply(cookie) {
+            if reply.status() == xcb::x::GrabStatus::Success {
+                return true;
+            }
+        }
+
+        std::thread::sleep(GRAB_RETRY_DELAY);
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hint_labels_are_single_keys_when_they_fit() {
+        assert_eq!(hint_labels(0), Vec::<String>::new());
+        assert_eq!(hint_labels(3), vec!["a", "s", "d"]);
+        assert_eq!(hint_labels(HINT_KEYS.len()).concat(), HINT_KEYS);
+    }
+
+    #[test]
+    fn hint_labels_are_all_the_same_length() {
+        let labels = hint_labels(HINT_KEYS.len() + 1);
+
+        assert_eq!(labels.len(), HINT_KEYS.len() + 1);
+        assert!(labels.iter().all(|label| label.len() == 2));
+        assert_eq!(labels[0], "aa");
+        assert_eq!(labels[1], "as");
+        assert_eq!(labels[HINT_KEYS.len()], "sa");
+    }
+
+    #[test]
+    fn hint_labels_are_unique() {
+        let mut labels = hint_labels(1000);
+        labels.sort();
+        labels.dedup();
+
+        assert_eq!(labels.len(), 1000);
+        assert!(labels.iter().all(|label| label.len() == 3));
+    }
+}
@@ -0,0 +1,430 @@
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    io::Write,
+    process::{Command, Stdio},
+    rc::Rc,
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Arc, Mutex,
+    },
+};
+
+use penrose::{
+    core::{xconnection::XConn, Hook},
+    WindowManager,
+};
+
+use crate::wake_wm;
+
+/// Something that can ask the user to pick one of a list of strings.
+pub trait Picker: Send + Sync {
+    /// Shows `items` and blocks until something is picked.
+    ///
+    /// This can be text that isn't one of `items`, if the picker lets the user type.
+    /// Returns `None` if it was cancelled.
+    ///
+    /// # Errors
+    /// Errors if the picker couldn't be shown.
+    fn pick(&self, prompt: &str, items: &[String]) -> std::io::Result<Option<String>>;
+}
+
+/// Runs a dmenu-like program, writing `items` to its stdin and reading the choice from stdout.
+fn run_menu(program: &str, args: &[&str], items: &[String]) -> std::io::Result<Option<String>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::BrokenPipe, "No stdin"))?
+        .write_all(items.join("\n").as_bytes())?;
+
+    let output = child.wait_with_output()?;
+
+    // Both rofi and dmenu exit with an error when cancelled.
+    if !output.status.success() {
+        return Ok(None);
+    }
+
+    let picked = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+    Ok(if picked.is_empty() {
+        None
+    } else {
+        Some(picked)
+    })
+}
+
+pub struct Rofi;
+
+impl Picker for Rofi {
+    fn pick(&self, prompt: &str, items: &[String]) -> std::io::Result<Option<String>> {
+        run_menu("rofi", &["-dmenu", "-i", "-p", prompt], items)
+    }
+}
+
+pub struct Dmenu;
+
+impl Picker for Dmenu {
+    fn pick(&self, prompt: &str, items: &[String]) -> std::io::Result<Option<String>> {
+        run_menu("dmenu", &["-i", "-p", prompt], items)
+    }
+}
+
+/// Gives pre-written answers instead of asking anyone, for testing things that use a picker.
+#[derive(Default)]
+pub struct ScriptedPicker {
+    answers: Mutex<VecDeque<Option<String>>>,
+    asked: Mutex<Vec<(String, Vec<String>)>>,
+}
+
+impl ScriptedPicker {
+    #[must_use]
+    pub fn new(answers: impl IntoIterator<Item = Option<String>>) -> Self {
+        Self {
+            answers: Mutex::new(answers.into_iter().collect()),
+            asked: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Every prompt and list of items this has been asked to pick from so far.
+    #[must_use]
+    pub fn asked(&self) -> Vec<(String, Vec<String>)> {
+        self.asked
+            .lock()
+            .map(|asked| asked.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Picker for ScriptedPicker {
+    fn pick(&self, prompt: &str, items: &[String]) -> std::io::Result<Option<String>> {
+        if let Ok(mut asked) = self.asked.lock() {
+            asked.push((prompt.to_string(), items.to_vec()));
+        }
+
+        Ok(self
+            .answers
+            .lock()
+            .ok()
+            .and_then(|mut answers| answers.pop_front())
+            .flatten())
+    }
+}
+
+type PickCallback<T> = Box<dyn FnOnce(&mut T, String) -> penrose::Result<()>>;
+
+struct PendingPick<T> {
+    receiver: Receiver<Option<String>>,
+    callback: PickCallback<T>,
+}
+
+/// Shows a picker on another thread, calling `wake` from there once something is picked.
+///
+/// The callback is queued in `pending` for `run_ready_picks`.
+fn queue_pick<T>(
+    picker: Arc<dyn Picker>,
+    wake: fn(),
+    pending: &RefCell<Vec<PendingPick<T>>>,
+    prompt: String,
+    items: Vec<String>,
+    callback: PickCallback<T>,
+) {
+    let (sender, receiver) = mpsc::channel();
+
+    std::thread::spawn(move || {
+        let picked = picker.pick(&prompt, &items).unwrap_or_else(|err| {
+            tracing::error!("Failed to show picker: {}", err);
+            None
+        });
+
+        let _ = sender.send(picked);
+        wake();
+    });
+
+    pending
+        .borrow_mut()
+        .push(PendingPick { receiver, callback });
+}
+
+/// Turns a callback taking the index of the item picked into one taking the item.
+fn index_callback<T: 'static>(
+    items: Vec<String>,
+    callback: impl FnOnce(&mut T, usize) -> penrose::Result<()> + 'static,
+) -> PickCallback<T> {
+    Box::new(move |target, picked| match picked_index(&items, &picked) {
+        Some(index) => callback(target, index),
+        None => Ok(()),
+    })
+}
+
+/// The index of the item that was picked, or `None` if something else was typed.
+fn picked_index(items: &[String], picked: &str) -> Option<usize> {
+    items.iter().position(|item| item == picked)
+}
+
+/// Runs the callbacks of the picks that have finished, keeping the rest queued.
+fn run_ready_picks<T>(pending: &RefCell<Vec<PendingPick<T>>>, target: &mut T) {
+    // Callbacks can queue more picks, so take them all out before running any.
+    let queued = std::mem::take(&mut *pending.borrow_mut());
+    let mut still_pending = vec![];
+
+    for pending_pick in queued {
+        match pending_pick.receiver.try_recv() {
+            Ok(Some(picked)) => {
+                if let Err(err) = (pending_pick.callback)(target, picked) {
+                    tracing::error!("Picker callback failed: {}", err);
+                }
+            }
+            Ok(None) | Err(TryRecvError::Disconnected) => {}
+            Err(TryRecvError::Empty) => still_pending.push(pending_pick),
+        }
+    }
+
+    pending.borrow_mut().extend(still_pending);
+}
+
+/// Shows pickers on another thread so the window manager keeps running,
+/// then runs a callback with the choice once the user has picked something.
+///
+/// The callbacks are run by the hook from `get_hook`, which must be added to the hooks.
+pub struct PickerQueue<X: XConn> {
+    picker: Arc<dyn Picker>,
+    pending: Rc<RefCell<Vec<PendingPick<WindowManager<X>>>>>,
+}
+
+impl<X: XConn> Clone for PickerQueue<X> {
+    fn clone(&self) -> Self {
+        Self {
+            picker: self.picker.clone(),
+            pending: self.pending.clone(),
+        }
+    }
+}
+
+impl<X: XConn + 'static> PickerQueue<X> {
+    #[must_use]
+    pub fn new(picker: impl Picker + 'static) -> Self {
+        Self {
+            picker: Arc::new(picker),
+            pending: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    #[must_use]
+    pub fn get_hook(&self) -> Box<PickerHook<X>> {
+        Box::new(PickerHook {
+            pending: self.pending.clone(),
+        })
+    }
+
+    /// Asks the user to pick from `items`, calling `callback` with what they picked.
+    ///
+    /// The callback isn't called if the picker was cancelled.
+    pub fn pick(
+        &self,
+        prompt: impl Into<String>,
+        items: Vec<String>,
+        callback: impl FnOnce(&mut WindowManager<X>, String) -> penrose::Result<()> + 'static,
+    ) {
+        queue_pick(
+            self.picker.clone(),
+            wake_wm,
+            &self.pending,
+            prompt.into(),
+            items,
+            Box::new(callback),
+        );
+    }
+
+    /// Like `pick`, but calls `callback` with the index of the item picked.
+    ///
+    /// Anything typed that isn't one of the items is ignored.
+    pub fn pick_index(
+        &self,
+        prompt: impl Into<String>,
+        items: Vec<String>,
+        callback: impl FnOnce(&mut WindowManager<X>, usize) -> penrose::Result<()> + 'static,
+    ) {
+        queue_pick(
+            self.picker.clone(),
+            wake_wm,
+            &self.pending,
+            prompt.into(),
+            items.clone(),
+            index_callback(items, callback),
+        );
+    }
+}
+
+pub struct PickerHook<X: XConn> {
+    pending: Rc<RefCell<Vec<PendingPick<WindowManager<X>>>>>,
+}
+
+impl<X: XConn> Hook<X> for PickerHook<X> {
+    fn event_handled(&mut self, wm: &mut WindowManager<X>) -> penrose::Result<()> {
+        run_ready_picks(&self.pending, wm);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<String> {
+        vec!["firefox".to_string(), "kitty".to_string()]
+    }
+
+    #[test]
+    fn scripted_picker_gives_answers_in_order() {
+        let picker = ScriptedPicker::new([Some("kitty".to_string()), None]);
+
+        assert_eq!(
+            picker.pick("open", &items()).unwrap(),
+            Some("kitty".to_string())
+        );
+        assert_eq!(picker.pick("open", &items()).unwrap(), None);
+        // Once the answers run out every pick is cancelled.
+        assert_eq!(picker.pick("open", &items()).unwrap(), None);
+
+        assert_eq!(picker.asked().len(), 3);
+        assert_eq!(picker.asked()[0], ("open".to_string(), items()));
+    }
+
+    #[test]
+    fn scripted_picker_can_type_text() {
+        let picker = ScriptedPicker::new([Some("not an item".to_string())]);
+
+        assert_eq!(
+            picker.pick("rename", &items()).unwrap(),
+            Some("not an item".to_string())
+        );
+    }
+
+    /// Runs the picks in `pending` as they finish, like `PickerHook` does.
+    fn run_all<T>(pending: &RefCell<Vec<PendingPick<T>>>, target: &mut T) {
+        for _ in 0..100 {
+            run_ready_picks(pending, target);
+
+            if pending.borrow().is_empty() {
+                return;
+            }
+
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        panic!("Picks never finished");
+    }
+
+    #[test]
+    fn queued_picks_call_back_with_the_item() {
+        let picker: Arc<dyn Picker> = Arc::new(ScriptedPicker::new([Some("kitty".to_string())]));
+        let pending = RefCell::new(vec![]);
+        let mut picked = vec![];
+
+        queue_pick(
+            picker,
+            || {},
+            &pending,
+            "open".to_string(),
+            items(),
+            Box::new(|picked: &mut Vec<String>, item| {
+                picked.push(item);
+                Ok(())
+            }),
+        );
+
+        run_all(&pending, &mut picked);
+
+        assert_eq!(picked, vec!["kitty".to_string()]);
+    }
+
+    #[test]
+    fn queued_index_picks_only_call_back_for_items() {
+        // The picks run at the same time, so which gets which answer isn't known.
+        let picker: Arc<dyn Picker> = Arc::new(ScriptedPicker::new([
+            Some("kitty".to_string()),
+            Some("not an item".to_string()),
+            None,
+        ]));
+        let pending = RefCell::new(vec![]);
+        let mut picked = vec![];
+
+        for _ in 0..3 {
+            queue_pick(
+                picker.clone(),
+                || {},
+                &pending,
+                "open".to_string(),
+                items(),
+                index_callback(items(), |picked: &mut Vec<usize>, index| {
+                    picked.push(index);
+                    Ok(())
+                }),
+            );
+        }
+
+        run_all(&pending, &mut picked);
+
+        assert_eq!(picked, vec![1]);
+    }
+
+    #[test]
+    fn callbacks_can_queue_more_picks() {
+        let picker: Arc<dyn Picker> = Arc::new(ScriptedPicker::new([
+            Some("firefox".to_string()),
+            Some("kitty".to_string()),
+        ]));
+        let pending = Rc::new(RefCell::new(vec![]));
+        let mut picked = vec![];
+
+        let inner_picker = picker.clone();
+        let inner_pending = pending.clone();
+        queue_pick(
+            picker,
+            || {},
+            &pending,
+            "first".to_string(),
+            items(),
+            Box::new(move |picked: &mut Vec<String>, item| {
+                picked.push(item);
+
+                queue_pick(
+                    inner_picker,
+                    || {},
+                    &inner_pending,
+                    "second".to_string(),
+                    items(),
+                    Box::new(|picked: &mut Vec<String>, item| {
+                        picked.push(item);
+                        Ok(())
+                    }),
+                );
+
+                Ok(())
+            }),
+        );
+
+        run_all(&pending, &mut picked);
+
+        assert_eq!(picked, vec!["firefox".to_string(), "kitty".to_string()]);
+    }
+
+    #[test]
+    fn picked_index_finds_items() {
+        assert_eq!(picked_index(&items(), "firefox"), Some(0));
+        assert_eq!(picked_index(&items(), "kitty"), Some(1));
+    }
+
+    #[test]
+    fn picked_index_ignores_typed_text() {
+        assert_eq!(picked_index(&items(), "kit"), None);
+        assert_eq!(picked_index(&[], "kitty"), None);
+    }
+}
@@ -0,0 +1,245 @@
+use penrose::{
+    core::{data_types::Region, xconnection::XConn},
+    Selector, WindowManager, Xid,
+};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    get_string_property, has_window_type, home, wm_instance_and_class, workspace_names,
+    WindowManagerExt,
+};
+
+/// Where the rules are read from.
+fn rules_path() -> String {
+    format!("{}/.local/share/penrose_arlo/rules.toml", home())
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Regex>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|pattern| Regex::new(&pattern).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Something to do to new windows that match, read from the rules file like this:
+///
+/// ```toml
+/// [[rule]]
+/// name = "password prompts"
+/// class = "vivaldi-stable"
+/// title = "^Save password"
+/// floating = true
+/// geometry = [0.3, 0.3, 0.4, 0.4]
+/// ```
+///
+/// Everything given to match on has to match.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowRule {
+    /// Used in the logs.
+    pub name: String,
+
+    /// The class from `WM_CLASS`, ignoring case.
+    pub class: Option<String>,
+    /// The instance from `WM_CLASS`, ignoring case.
+    pub instance: Option<String>,
+    /// A regex the title has to match.
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub title: Option<Regex>,
+    /// The `WM_WINDOW_ROLE`.
+    pub role: Option<String>,
+    /// A type from `_NET_WM_WINDOW_TYPE` without the prefix, like "dialog".
+    pub window_type: Option<String>,
+
+    /// The name of the workspace to put it on.
+    pub workspace: Option<String>,
+    /// The screen to put it on, counting from the left starting at 0.
+    pub screen: Option<usize>,
+    pub floating: bool,
+    /// Where it floats as `[x, y, w, h]`, each a fraction of the screen.
+    pub geometry: Option<[f32; 4]>,
+    pub fullscreen: bool,
+    pub sticky: bool,
+    /// The border width in pixels.
+    pub border: Option<u32>,
+    /// Don't focus it when it opens.
+    pub no_focus: bool,
+}
+
+impl WindowRule {
+    /// Whether the window `id` matches everything this rule matches on.
+    #[must_use]
+    pub fn matches<X: XConn>(&self, wm: &WindowManager<X>, id: Xid) -> bool {
+        let (instance, class) = wm_instance_and_class(id).unwrap_or_default();
+
+        let title = wm
+            .client(&Selector::WinId(id))
+            .map(|client| client.wm_name().to_string())
+            .unwrap_or_default();
+
+        self.class
+            .as_ref()
+            .map_or(true, |wanted| wanted.eq_ignore_ascii_case(&class))
+            && self
+                .instance
+                .as_ref()
+                .map_or(true, |wanted| wanted.eq_ignore_ascii_case(&instance))
+            && self
+                .title
+                .as_ref()
+                .map_or(true, |regex| regex.is_match(&title))
+            && self.role.as_ref().map_or(true, |wanted| {
+                get_string_property(id, "WM_WINDOW_ROLE").as_ref() == Some(wanted)
+            })
+            && self
+                .window_type
+                .as_ref()
+                .map_or(true, |wanted| has_window_type(id, wanted))
+    }
+
+    /// The workspace this rule puts windows on, if it says.
+    #[must_use]
+    pub fn workspace<X: XConn>(&self, wm: &WindowManager<X>) -> Option<usize> {
+        if let Some(name) = &self.workspace {
+            return workspace_names(wm).iter().position(|other| other == name);
+        }
+
+        let screen = *wm.screens_by_position().get(self.screen?)?;
+        wm.screen(&Selector::Index(screen)).map(|screen| screen.wix)
+    }
+
+    /// The region this rule floats windows in on `screen`, if it says.
+    #[must_use]
+    pub fn region_on(&self, screen: &Region) -> Option<Region> {
+        let [x, y, w, h] = self.geometry?;
+
+        Some(Region::new(
+            screen.x + (screen.w as f32 * x) as u32,
+            screen.y + (screen.h as f32 * y) as u32,
+            (screen.w as f32 * w) as u32,
+            (screen.h as f32 * h) as u32,
+        ))
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RulesFile {
+    rule: Vec<WindowRule>,
+}
+
+/// Reads the rules file, logging and returning no rules if it can't be read.
+#[must_use]
+pub fn load_rules() -> Vec<WindowRule> {
+    let path = rules_path();
+
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) => {
+            tracing::info!("Not using window rules from {}: {}", path, err);
+            return vec![];
+        }
+    };
+
+    match toml::from_str::<RulesFile>(&contents) {
+        Ok(file) => file.rule,
+        Err(err) => {
+            tracing::error!("Failed to parse window rules from {}: {}", path, err);
+            vec![]
+        }
+    }
+}
+
+/// The index of the first rule that matches the window `id`.
+#[must_use]
+pub fn find_rule<X: XConn>(rules: &[WindowRule], wm: &WindowManager<X>, id: Xid) -> Option<usize> {
+    let index = rules.iter().position(|rule| rule.matches(wm, id))?;
+
+    tracing::debug!("Window rule \"{}\" matched {}", rules[index].name, id);
+
+    Some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_are_parsed() {
+        let file = toml::from_str::<RulesFile>(
+            r#"
+            [[rule]]
+            name = "password prompts"
+            class = "vivaldi-stable"
+            title = "^Save password"
+            floating = true
+            geometry = [0.25, 0.25, 0.5, 0.5]
+
+            [[rule]]
+            instance = "spotify"
+            workspace = "music"
+            no_focus = true
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(file.rule.len(), 2);
+
+        let prompts = &file.rule[0];
+        assert_eq!(prompts.name, "password prompts");
+        assert_eq!(prompts.class.as_deref(), Some("vivaldi-stable"));
+        assert!(prompts.title.as_ref().unwrap().is_match("Save password?"));
+        assert!(!prompts
+            .title
+            .as_ref()
+            .unwrap()
+            .is_match("Don't Save password"));
+        assert!(prompts.floating);
+        assert_eq!(prompts.workspace, None);
+
+        let spotify = &file.rule[1];
+        assert_eq!(spotify.name, "");
+        assert_eq!(spotify.instance.as_deref(), Some("spotify"));
+        assert_eq!(spotify.workspace.as_deref(), Some("music"));
+        assert!(spotify.no_focus);
+        assert!(!spotify.floating);
+        assert_eq!(spotify.geometry, None);
+    }
+
+    #[test]
+    fn example_rules_are_valid() {
+        let file = toml::from_str::<RulesFile>(include_str!("../rules.example.toml")).unwrap();
+
+        assert!(!file.rule.is_empty());
+    }
+
+    #[test]
+    fn empty_rules_files_have_no_rules() {
+        assert!(toml::from_str::<RulesFile>("").unwrap().rule.is_empty());
+    }
+
+    #[test]
+    fn unknown_fields_and_bad_regexes_are_errors() {
+        assert!(toml::from_str::<RulesFile>("[[rule]]\nclas = \"kitty\"").is_err());
+        assert!(toml::from_str::<RulesFile>("[[rule]]\ntitle = \"(\"").is_err());
+    }
+
+    #[test]
+    fn region_on_scales_to_the_screen() {
+        let rule = WindowRule {
+            geometry: Some([0.25, 0.5, 0.5, 0.25]),
+            ..WindowRule::default()
+        };
+
+        assert_eq!(
+            rule.region_on(&Region::new(1920, 0, 1000, 800)),
+            Some(Region::new(2170, 400, 500, 200))
+        );
+        assert_eq!(
+            WindowRule::default().region_on(&Region::new(0, 0, 1000, 800)),
+            None
+        );
+    }
+}
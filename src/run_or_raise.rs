@@ -0,0 +1,87 @@
+use penrose::{
+    core::{helpers::spawn, xconnection::XConn, Client},
+    Selector, WindowManager,
+};
+
+use crate::{is_hidden, raise_window, WindowManagerExt, FOCUS_HISTORY};
+
+/// Picks out clients by their class or title.
+#[derive(Clone)]
+pub enum ClientMatcher {
+    /// The `WM_CLASS`, ignoring case.
+    Class(String),
+    /// Part of the title, ignoring case.
+    Title(String),
+    /// Any of these match.
+    Any(Vec<ClientMatcher>),
+}
+
+impl ClientMatcher {
+    #[must_use]
+    pub fn class(class: impl Into<String>) -> Self {
+        Self::Class(class.into())
+    }
+
+    #[must_use]
+    pub fn title(title: impl Into<String>) -> Self {
+        Self::Title(title.into())
+    }
+
+    #[must_use]
+    pub fn matches(&self, client: &Client) -> bool {
+        match self {
+            Self::Class(class) => client.wm_class().eq_ignore_ascii_case(class),
+            Self::Title(title) => client
+                .wm_name()
+                .to_lowercase()
+                .contains(&title.to_lowercase()),
+            Self::Any(matchers) => matchers.iter().any(|matcher| matcher.matches(client)),
+        }
+    }
+}
+
+/// Focuses and raises a client that matches, wherever it is,
+/// or runs `command` if there isn't one that isn't hidden.
+///
+/// Pressing it again while one is focused goes to the next one that matches.
+///
+/// # Errors
+/// Errors if `command` couldn't be spawned, or if an inner penrose command fails.
+pub fn run_or_raise<X: XConn>(
+    wm: &mut WindowManager<X>,
+    matcher: &ClientMatcher,
+    command: impl Into<String>,
+) -> penrose::Result<()> {
+    let mut matching = wm
+        .all_clients(&Selector::Condition(&|client: &Client| {
+            matcher.matches(client)
+        }))
+        .into_iter()
+        .map(|client| client.id())
+        .filter(|id| !is_hidden(*id))
+        .collect::<Vec<_>>();
+
+    if matching.is_empty() {
+        return spawn(command);
+    }
+
+    matching.sort_unstable();
+
+    let target = match wm
+        .focused_client_id()
+        .and_then(|focused| matching.iter().position(|id| *id == focused))
+    {
+        Some(position) => matching[(position + 1) % matching.len()],
+        // Start with the one used last.
+        None => FOCUS_HISTORY
+            .read()
+            .ok()
+            .and_then(|history| history.iter().find(|id| matching.contains(id)).copied())
+            .unwrap_or(matching[0]),
+    };
+
+    wm.focus_client_anywhere(target)?;
+    raise_window(target);
+
+    Ok(())
+}
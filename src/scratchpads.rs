@@ -0,0 +1,361 @@
+use std::{cell::RefCell, rc::Rc};
+
+use penrose::{
+    core::{data_types::Region, helpers::spawn, xconnection::XConn, Client, Hook},
+    PenroseError, Selector, WindowManager, Xid,
+};
+
+use crate::{raise_window, BetterKeyBindings, ClientMatcher, PickerQueue, WindowManagerExt};
+
+#[derive(Clone, Copy)]
+pub enum ScratchpadPosition {
+    Centered,
+    /// How far across and down the screen it is, from 0 to 1.
+    At(f32, f32),
+}
+
+#[derive(Clone)]
+pub struct NamedScratchpad {
+    pub name: String,
+    pub command: String,
+    /// Picks out the window the command opens.
+    pub matcher: ClientMatcher,
+    /// The width as a fraction of the screen.
+    pub w: f32,
+    /// The height as a fraction of the screen.
+    pub h: f32,
+    pub position: ScratchpadPosition,
+    pub key: String,
+}
+
+impl NamedScratchpad {
+    #[must_use]
+    pub fn new(
+        name: impl Into<String>,
+        command: impl Into<String>,
+        matcher: ClientMatcher,
+        key: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            command: command.into(),
+            matcher,
+            w: 0.8,
+            h: 0.8,
+            position: ScratchpadPosition::Centered,
+            key: key.into(),
+        }
+    }
+
+    /// Sets the size as fractions of the screen, which can't be more than 1.
+    #[must_use]
+    pub fn size(mut self, w: f32, h: f32) -> Self {
+        self.w = w.clamp(0., 1.);
+        self.h = h.clamp(0., 1.);
+        self
+    }
+
+    #[must_use]
+    pub fn at(mut self, x: f32, y: f32) -> Self {
+        self.position = ScratchpadPosition::At(x, y);
+        self
+    }
+
+    /// Where this should go on a screen.
+    fn region_on(&self, screen: &Region) -> Region {
+        let w = (screen.w as f32 * self.w) as u32;
+        let h = (screen.h as f32 * self.h) as u32;
+
+        let (x, y) = match self.position {
+            ScratchpadPosition::Centered => (0.5, 0.5),
+            ScratchpadPosition::At(x, y) => (x, y),
+        };
+
+        Region::new(
+            screen.x + (screen.w.saturating_sub(w) as f32 * x) as u32,
+            screen.y + (screen.h.saturating_sub(h) as f32 * y) as u32,
+            w,
+            h,
+        )
+    }
+}
+
+struct ScratchpadState {
+    config: NamedScratchpad,
+    client: Option<Xid>,
+    visible: bool,
+    /// The command has been run, but its window hasn't appeared yet.
+    pending: bool,
+}
+
+/// Any number of scratchpads, each shown on the screen under the pointer when toggled.
+///
+/// The hook from `get_hook` must be added to the hooks.
+#[derive(Clone, Default)]
+pub struct ScratchpadManager {
+    scratchpads: Rc<RefCell<Vec<ScratchpadState>>>,
+}
+
+impl ScratchpadManager {
+    #[must_use]
+    pub fn new(scratchpads: impl IntoIterator<Item = NamedScratchpad>) -> Self {
+        Self {
+            scratchpads: Rc::new(RefCell::new(
+                scratchpads
+                    .into_iter()
+                    .map(|config| ScratchpadState {
+                        config,
+                        client: None,
+                        visible: false,
+                        pending: false,
+                    })
+                    .collect(),
+            )),
+        }
+    }
+
+    #[must_use]
+    pub fn get_hook(&self) -> Box<ScratchpadHook> {
+        Box::new(ScratchpadHook {
+            scratchpads: self.scratchpads.clone(),
+        })
+    }
+
+    /// Adds a binding to toggle each scratchpad using its key.
+    pub fn bind<X: XConn + 'static>(&self, keys: &mut BetterKeyBindings<X>) {
+        for scratchpad in self.scratchpads.borrow().iter() {
+            let manager = self.clone();
+            let name = scratchpad.config.name.clone();
+
+            keys.add(scratchpad.config.key.clone(), move |wm| {
+                manager.toggle(wm, &name)
+            });
+        }
+    }
+
+    fn index_of(&self, name: &str) -> penrose::Result<usize> {
+        self.scratchpads
+            .borrow()
+            .iter()
+            .position(|scratchpad| scratchpad.config.name == name)
+            .ok_or(PenroseError::Raw(format!("No scratchpad called {name}")))
+    }
+
+    /// Shows the scratchpad if it's hidden and hides it if it's shown.
+    ///
+    /// If its window doesn't exist, an existing window that matches is used,
+    /// or else the command is run again.
+    ///
+    /// # Errors
+    /// Errors if there is no scratchpad called `name`, or if an inner penrose command fails.
+    pub fn toggle<X: XConn>(&self, wm: &mut WindowManager<X>, name: &str) -> penrose::Result<()> {
+        let index = self.index_of(name)?;
+
+        let (client, visible) = {
+            let scratchpads = self.scratchpads.borrow();
+            (scratchpads[index].client, scratchpads[index].visible)
+        };
+
+        match client {
+            Some(id) if visible => {
+                self.scratchpads.borrow_mut()[index].visible = false;
+                wm.hide_client(id)
+            }
+            Some(_) => self.show(wm, index),
+            None => {
+                let existing = {
+                    let scratchpads = self.scratchpads.borrow();
+                    let matcher = &scratchpads[index].config.matcher;
+
+                    wm.client(&Selector::Condition(&|client: &Client| {
+                        matcher.matches(client)
+                    }))
+                    .map(|client| client.id())
+                };
+
+                match existing {
+                    Some(id) => self.adopt(wm, index, id),
+                    None => {
+                        let mut scratchpads = self.scratchpads.borrow_mut();
+                        scratchpads[index].pending = true;
+                        spawn(scratchpads[index].config.command.clone())
+                    }
+                }
+            }
+        }
+    }
+
+    /// Lists the scratchpads in the picker, and makes the focused window the one picked.
+    pub fn adopt_focused_picker<X: XConn + 'static>(&self, pickers: &PickerQueue<X>) {
+        let names = self
+            .scratchpads
+            .borrow()
+            .iter()
+            .map(|scratchpad| scratchpad.config.name.clone())
+            .collect::<Vec<_>>();
+
+        let manager = self.clone();
+
+        pickers.pick_index("scratchpad", names.clone(), move |wm, index| {
+            manager.adopt_focused(wm, &names[index])
+        });
+    }
+
+    /// Makes the focused window the scratchpad called `name`.
+    ///
+    /// # Errors
+    /// Errors if there is no scratchpad called `name`, or if an inner penrose command fails.
+    pub fn adopt_focused<X: XConn>(
+        &self,
+        wm: &mut WindowManager<X>,
+        name: &str,
+    ) -> penrose::Result<()> {
+        let index = self.index_of(name)?;
+
+        match wm.focused_client_id() {
+            Some(id) => self.adopt(wm, index, id),
+            None => Ok(()),
+        }
+    }
+
+    fn adopt<X: XConn>(
+        &self,
+        wm: &mut WindowManager<X>,
+        index: usize,
+        id: Xid,
+    ) -> penrose::Result<()> {
+        {
+            let mut scratchpads = self.scratchpads.borrow_mut();
+
+            // A window can only be one scratchpad at a time.
+            for scratchpad in scratchpads.iter_mut() {
+                if scratchpad.client == Some(id) {
+                    scratchpad.client = None;
+                    scratchpad.visible = false;
+                }
+            }
+
+            scratchpads[index].client = Some(id);
+            scratchpads[index].pending = false;
+        }
+
+        if let Some(client) = wm.client_mut(&Selector::WinId(id)) {
+            client.externally_managed();
+        }
+
+        wm.layout_client_screen(id)?;
+
+        self.show(wm, index)
+    }
+
+    fn show<X: XConn>(&self, wm: &mut WindowManager<X>, index: usize) -> penrose::Result<()> {
+        let (id, region, workspace) = {
+            let scratchpads = self.scratchpads.borrow();
+            let scratchpad = &scratchpads[index];
+
+            let screen_index = wm
+                .screen_under_pointer()
+                .unwrap_or(wm.active_screen_index());
+            let screen = wm
+                .screen(&Selector::Index(screen_index))
+                .ok_or(PenroseError::Raw("No screen under the pointer".to_string()))?;
+
+            match scratchpad.client {
+                Some(id) => (
+                    id,
+                    scratchpad.config.region_on(&screen.region(false)),
+                    screen.wix,
+                ),
+                None => return Ok(()),
+            }
+        };
+
+        self.scratchpads.borrow_mut()[index].visible = true;
+
+        // Otherwise penrose thinks it's still on the workspace it was first opened on,
+        // and hides or shows it along with that one.
+        wm.move_client_to_workspace(id, workspace)?;
+        wm.position_client(id, region, true)?;
+        wm.show_client(id)?;
+        let _ = wm.focus_client(&Selector::WinId(id));
+        raise_window(id);
+
+        Ok(())
+    }
+}
+
+pub struct ScratchpadHook {
+    scratchpads: Rc<RefCell<Vec<ScratchpadState>>>,
+}
+
+impl ScratchpadHook {
+    fn manager(&self) -> ScratchpadManager {
+        ScratchpadManager {
+            scratchpads: self.scratchpads.clone(),
+        }
+    }
+}
+
+impl<X: XConn> Hook<X> for ScratchpadHook {
+    // This waits until penrose has put the window on a workspace,
+    // so that moving it to the one it's shown on doesn't get undone.
+    fn client_added_to_workspace(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        _workspace_index: usize,
+    ) -> penrose::Result<()> {
+        let index = {
+            let client = match wm.client(&Selector::WinId(id)) {
+                Some(client) => client,
+                None => return Ok(()),
+            };
+
+            self.scratchpads.borrow().iter().position(|scratchpad| {
+                scratchpad.pending && scratchpad.config.matcher.matches(client)
+            })
+        };
+
+        match index {
+            Some(index) => self.manager().adopt(wm, index, id),
+            None => Ok(()),
+        }
+    }
+
+    fn workspace_change(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        _previous_workspace: usize,
+        _new_workspace: usize,
+    ) -> penrose::Result<()> {
+        // Penrose hides scratchpads along with the workspace they were shown on.
+        let visible_workspaces = wm.focused_workspaces();
+
+        for scratchpad in self.scratchpads.borrow_mut().iter_mut() {
+            let on_visible_workspace = scratchpad
+                .client
+                .and_then(|id| wm.client(&Selector::WinId(id)))
+                .map_or(false, |client| {
+                    visible_workspaces.contains(&client.workspace())
+                });
+
+            if !on_visible_workspace {
+                scratchpad.visible = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn remove_client(&mut self, _wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        // The next toggle will run the command again.
+        for scratchpad in self.scratchpads.borrow_mut().iter_mut() {
+            if scratchpad.client == Some(id) {
+                scratchpad.client = None;
+                scratchpad.visible = false;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -11,6 +11,8 @@ pub fn setup_logger() {
     let log_file = format!("{}/.penrose.log", home());
 
     simplelog::WriteLogger::init(
+        // Penrose logs a lot at the debug level,
+        // so use Debug only to see things like which window rule matched.
         simplelog::LevelFilter::Info,
         simplelog::Config::default(),
         std::fs::File::create(log_file).expect("Failed to create log file"),
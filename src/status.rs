@@ -0,0 +1,82 @@
+use penrose::{
+    core::{xconnection::XConn, Hook},
+    Selector, WindowManager, Xid,
+};
+
+use crate::{focus_cycle_active, marks_of, urgent_clients};
+
+/// Sets the root window name, which the bar shows,
+/// to the layout symbol, the marks on the focused client and anything that needs attention.
+///
+/// # Errors
+/// Errors if the root window name couldn't be set.
+pub fn update_root_name<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    let mut parts = vec![wm.current_layout_symbol().to_string()];
+
+    if let Some(id) = wm.focused_client_id() {
+        parts.extend(marks_of(id).into_iter().map(|mark| format!("'{mark}")));
+    }
+
+    let mut urgent_workspaces = urgent_clients()
+        .into_iter()
+        .filter_map(|id| {
+            wm.client(&Selector::WinId(id))
+                .map(|client| client.workspace())
+        })
+        .collect::<Vec<_>>();
+    urgent_workspaces.sort_unstable();
+    urgent_workspaces.dedup();
+
+    parts.extend(urgent_workspaces.into_iter().map(|index| {
+        let name = wm
+            .workspace(&Selector::Index(index))
+            .map_or_else(|| index.to_string(), |ws| ws.name().to_string());
+
+        format!("!{name}")
+    }));
+
+    wm.set_root_window_name(&parts.join(" "))
+}
+
+/// Keeps the root window name up to date,
+/// except while it shows the preview of an alt-tab cycle.
+pub struct StatusHook {}
+
+impl StatusHook {
+    #[must_use]
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+
+    fn update<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+        if focus_cycle_active() {
+            return Ok(());
+        }
+
+        update_root_name(wm)
+    }
+}
+
+impl<X: XConn> Hook<X> for StatusHook {
+    fn layout_change(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        _workspace_index: usize,
+        _screen_index: usize,
+    ) -> penrose::Result<()> {
+        Self::update(wm)
+    }
+
+    fn workspace_change(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        _previous_workspace: usize,
+        _new_workspace: usize,
+    ) -> penrose::Result<()> {
+        Self::update(wm)
+    }
+
+    fn focus_change(&mut self, wm: &mut WindowManager<X>, _id: Xid) -> penrose::Result<()> {
+        Self::update(wm)
+    }
+}
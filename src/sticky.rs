@@ -0,0 +1,224 @@
+use std::{collections::HashSet, sync::RwLock};
+
+use lazy_static::lazy_static;
+use penrose::{
+    core::{data_types::Region, xconnection::XConn, Hook},
+    PenroseError, Selector, WindowManager, Xid,
+};
+
+use crate::{
+    add_net_wm_state, center_within, is_hidden, is_tiled, make_floating, make_tiled, raise_window,
+    remove_net_wm_state, screen_region_containing, window_region, WindowManagerExt,
+};
+
+/// How big picture-in-picture windows are, as a fraction of the screen.
+const PIP_SIZE: f32 = 0.25;
+
+lazy_static! {
+    /// Clients that stay on their screen whichever workspace it shows.
+    static ref STICKY: RwLock<HashSet<Xid>> = RwLock::new(HashSet::new());
+}
+
+fn sticky_poisoned<T>(_: T) -> PenroseError {
+    PenroseError::Raw("STICKY RwLock poisoned".into())
+}
+
+#[derive(Clone, Copy)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Corner {
+    /// A region of size `w` by `h` in this corner of `screen`, `gap` pixels from the edges.
+    #[must_use]
+    pub const fn region_in(self, screen: &Region, w: u32, h: u32, gap: u32) -> Region {
+        let left = screen.x + gap;
+        let top = screen.y + gap;
+        let right = (screen.x + screen.w).saturating_sub(w + gap);
+        let bottom = (screen.y + screen.h).saturating_sub(h + gap);
+
+        let (x, y) = match self {
+            Self::TopLeft => (left, top),
+            Self::TopRight => (right, top),
+            Self::BottomLeft => (left, bottom),
+            Self::BottomRight => (right, bottom),
+        };
+
+        Region::new(x, y, w, h)
+    }
+}
+
+#[must_use]
+pub fn is_sticky(id: Xid) -> bool {
+    STICKY.read().map_or(false, |sticky| sticky.contains(&id))
+}
+
+/// Makes a client stay visible on every workspace of its screen, or stops it.
+///
+/// # Errors
+/// Errors if the `STICKY` lock is poisoned.
+pub fn set_sticky(id: Xid, sticky: bool) -> penrose::Result<()> {
+    let mut sticky_clients = STICKY.write().map_err(sticky_poisoned)?;
+
+    if sticky {
+        sticky_clients.insert(id);
+        add_net_wm_state(id, "_NET_WM_STATE_STICKY");
+    } else {
+        sticky_clients.remove(&id);
+        remove_net_wm_state(id, "_NET_WM_STATE_STICKY");
+    }
+
+    Ok(())
+}
+
+/// Toggles whether the focused client is sticky.
+///
+/// # Errors
+/// Errors if the `STICKY` lock is poisoned.
+pub fn toggle_sticky<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    match wm.focused_client_id() {
+        Some(id) => set_sticky(id, !is_sticky(id)),
+        None => Ok(()),
+    }
+}
+
+/// Makes the focused client a small, sticky, floating window in a corner of its screen,
+/// or puts it back into the layout if it already is sticky.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn picture_in_picture<X: XConn>(
+    wm: &mut WindowManager<X>,
+    corner: Corner,
+    gap_px: u32,
+) -> penrose::Result<()> {
+    let id = match wm.focused_client_id() {
+        Some(id) => id,
+        None => return Ok(()),
+    };
+
+    if is_sticky(id) {
+        set_sticky(id, false)?;
+        return make_tiled(wm, id);
+    }
+
+    if is_tiled(wm, id)? {
+        make_floating(wm, id)?;
+    }
+
+    let region = window_region(id).ok_or(PenroseError::Raw(format!(
+        "Couldn't get the geometry of {id}"
+    )))?;
+    let screen = screen_region_containing(wm, &region)?;
+
+    let w = (screen.w as f32 * PIP_SIZE) as u32;
+    let h = (screen.h as f32 * PIP_SIZE) as u32;
+
+    set_sticky(id, true)?;
+    wm.position_client(id, corner.region_in(&screen, w, h, gap_px), true)?;
+    raise_window(id);
+
+    Ok(())
+}
+
+/// Moves sticky clients whose workspace isn't showing any more
+/// onto the workspace now showing on the screen they were on.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn follow_sticky<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    let sticky = STICKY.read().map_err(sticky_poisoned)?.clone();
+    let visible_workspaces = wm.focused_workspaces();
+
+    let mut moving = vec![];
+    for id in sticky {
+        let left_behind = wm.client(&Selector::WinId(id)).map_or(false, |client| {
+            !visible_workspaces.contains(&client.workspace())
+        });
+
+        if !left_behind || is_hidden(id) {
+            continue;
+        }
+
+        let screen = window_region(id)
+            .and_then(|region| {
+                (0..wm.n_screens()).find(|index| {
+                    wm.screen(&Selector::Index(*index)).map_or(false, |screen| {
+                        center_within(&region, &screen.region(false))
+                    })
+                })
+            })
+            .unwrap_or(wm.active_screen_index());
+
+        if let Some(workspace) = wm.screen(&Selector::Index(screen)).map(|screen| screen.wix) {
+            moving.push((id, screen, workspace));
+        }
+    }
+
+    if moving.is_empty() {
+        return Ok(());
+    }
+
+    for (id, _screen, workspace) in &moving {
+        wm.move_client_to_workspace(*id, *workspace)?;
+        wm.show_client(*id)?;
+    }
+
+    let mut screens = moving
+        .iter()
+        .map(|(_id, screen, _workspace)| *screen)
+        .collect::<Vec<_>>();
+    screens.sort_unstable();
+    screens.dedup();
+
+    for screen in screens {
+        wm.layout_screen(screen)?;
+    }
+
+    // Floating ones would otherwise end up behind the new workspace's windows.
+    for (id, _screen, _workspace) in moving {
+        raise_window(id);
+    }
+
+    Ok(())
+}
+
+/// Brings sticky clients along when their screen changes workspace,
+/// and forgets them when they close.
+///
+/// Penrose doesn't run hooks for workspace changes made by other hooks,
+/// like picker callbacks or scrolling on the root window,
+/// so this checks after every event too.
+/// It should come after the hooks that can change workspace.
+pub struct StickyHook {}
+
+impl StickyHook {
+    #[must_use]
+    pub fn new() -> Box<Self> {
+        Box::new(Self {})
+    }
+}
+
+impl<X: XConn> Hook<X> for StickyHook {
+    fn workspace_change(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        _previous_workspace: usize,
+        _new_workspace: usize,
+    ) -> penrose::Result<()> {
+        follow_sticky(wm)
+    }
+
+    fn event_handled(&mut self, wm: &mut WindowManager<X>) -> penrose::Result<()> {
+        follow_sticky(wm)
+    }
+
+    fn remove_client(&mut self, _wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        STICKY.write().map_err(sticky_poisoned)?.remove(&id);
+
+        Ok(())
+    }
+}
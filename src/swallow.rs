@@ -0,0 +1,290 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::RwLock,
+};
+
+use lazy_static::lazy_static;
+use penrose::{
+    core::{ring::InsertPoint, xconnection::XConn, Client, Hook},
+    PenroseError, Selector, WindowManager, Xid,
+};
+
+use crate::{transient_for, window_pid};
+
+/// How far up the process tree to look for a terminal.
+const MAX_PROCESS_DEPTH: usize = 16;
+
+lazy_static! {
+    /// Terminals that are hidden because a window opened from them swallowed them.
+    static ref SWALLOWED_TERMINALS: RwLock<HashSet<Xid>> = RwLock::new(HashSet::new());
+}
+
+fn swallowed_poisoned<T>(_: T) -> PenroseError {
+    PenroseError::Raw("SWALLOWED_TERMINALS RwLock poisoned".into())
+}
+
+/// Whether a terminal is hidden because a window opened from it swallowed it.
+#[must_use]
+pub fn is_swallowed(id: Xid) -> bool {
+    SWALLOWED_TERMINALS
+        .read()
+        .map_or(false, |swallowed| swallowed.contains(&id))
+}
+
+/// The parent of a process, from `/proc/<pid>/stat`.
+fn parent_pid(pid: u32) -> Option<u32> {
+    parse_parent_pid(&std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?)
+}
+
+/// The parent pid from the contents of a `/proc/<pid>/stat` file.
+fn parse_parent_pid(stat: &str) -> Option<u32> {
+    // The command name is in brackets and can have spaces in it,
+    // so start after it. The state comes next, then the parent.
+    stat[stat.rfind(')')? + 1..]
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+struct Swallowed {
+    terminal: Xid,
+    workspace: usize,
+}
+
+/// Hides a terminal when a window is opened from it, putting the new window in its place,
+/// then brings the terminal back when that window closes.
+///
+/// Terminals are matched to windows through `_NET_WM_PID`
+/// and the parents of the window's process.
+pub struct SwallowHook {
+    /// The classes of terminals that can swallow windows.
+    terminals: HashSet<String>,
+    /// The classes of windows that are never swallowed.
+    deny: HashSet<String>,
+    /// Windows that swallowed terminals, and what they swallowed.
+    swallowed: HashMap<Xid, Swallowed>,
+}
+
+impl SwallowHook {
+    #[must_use]
+    pub fn new<S: Into<String>>(
+        terminals: impl IntoIterator<Item = S>,
+        deny: impl IntoIterator<Item = S>,
+    ) -> Box<Self> {
+        Box::new(Self {
+            terminals: terminals.into_iter().map(Into::into).collect(),
+            deny: deny.into_iter().map(Into::into).collect(),
+            swallowed: HashMap::new(),
+        })
+    }
+
+    /// The terminal that `id` was opened from, if it's one that can swallow it.
+    fn find_terminal<X: XConn>(&self, wm: &WindowManager<X>, id: Xid) -> Option<Xid> {
+        let class = wm.client(&Selector::WinId(id))?.wm_class();
+        if self.deny.contains(class) || self.terminals.contains(class) {
+            return None;
+        }
+
+        // Dialogs float over their parent instead.
+        if transient_for(id).is_some() {
+            return None;
+        }
+
+        // Terminals that have already swallowed a window aren't on a workspace any more,
+        // and can't swallow another.
+        let mut terminals = HashMap::<u32, Vec<Xid>>::new();
+        for terminal in wm
+            .all_clients(&Selector::Condition(&|client: &Client| {
+                self.terminals.contains(client.wm_class())
+            }))
+            .into_iter()
+            .map(|client| client.id())
+            .filter(|terminal| {
+                wm.all_workspaces(&Selector::Any)
+                    .iter()
+                    .any(|workspace| workspace.client_ids().contains(terminal))
+            })
+        {
+            if let Some(pid) = window_pid(terminal) {
+                terminals.entry(pid).or_default().push(terminal);
+            }
+        }
+
+        let mut pid = window_pid(id)?;
+        for _ in 0..MAX_PROCESS_DEPTH {
+            pid = parent_pid(pid)?;
+
+            match terminals.get(&pid).map(Vec::as_slice) {
+                Some([terminal]) => return Some(*terminal),
+                // Terminals that share a process can't tell which window it came from.
+                Some(_) => return None,
+                None => {}
+            }
+        }
+
+        None
+    }
+
+    fn swallow<X: XConn>(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        current_workspace: usize,
+        terminal: Xid,
+    ) -> penrose::Result<()> {
+        let workspace = wm
+            .client(&Selector::WinId(terminal))
+            .ok_or(PenroseError::Raw(format!("No client with id {terminal}")))?
+            .workspace();
+
+        if let Some(ws) = wm.workspace_mut(&Selector::Index(current_workspace)) {
+            ws.remove_client(id);
+        }
+
+        let ws = wm
+            .workspace_mut(&Selector::Index(workspace))
+            .ok_or(PenroseError::Raw(format!("No workspace {workspace}")))?;
+        let index = ws
+            .client_ids()
+            .iter()
+            .position(|other| *other == terminal)
+            .unwrap_or_default();
+        ws.remove_client(terminal);
+        ws.add_client(id, &InsertPoint::Index(index))?;
+
+        if let Some(client) = wm.client_mut(&Selector::WinId(id)) {
+            client.set_workspace(workspace);
+        }
+
+        wm.hide_client(terminal)?;
+
+        SWALLOWED_TERMINALS
+            .write()
+            .map_err(swallowed_poisoned)?
+            .insert(terminal);
+        self.swallowed.insert(
+            id,
+            Swallowed {
+                terminal,
+                workspace,
+            },
+        );
+
+        relayout_showing(wm, &[current_workspace, workspace])?;
+        wm.focus_client(&Selector::WinId(id))?;
+
+        Ok(())
+    }
+
+    fn release<X: XConn>(
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        swallowed: &Swallowed,
+    ) -> penrose::Result<()> {
+        SWALLOWED_TERMINALS
+            .write()
+            .map_err(swallowed_poisoned)?
+            .remove(&swallowed.terminal);
+
+        let ws = wm
+            .workspace_mut(&Selector::Index(swallowed.workspace))
+            .ok_or(PenroseError::Raw(format!(
+                "No workspace {}",
+                swallowed.workspace
+            )))?;
+
+        // The window that closed might not be out of the workspace yet.
+        let insert_point = match ws.client_ids().iter().position(|other| *other == id) {
+            Some(index) => InsertPoint::Index(index),
+            None => InsertPoint::Focused,
+        };
+        ws.add_client(swallowed.terminal, &insert_point)?;
+
+        if let Some(client) = wm.client_mut(&Selector::WinId(swallowed.terminal)) {
+            client.set_workspace(swallowed.workspace);
+        }
+
+        wm.show_client(swallowed.terminal)?;
+
+        relayout_showing(wm, &[swallowed.workspace])?;
+        wm.focus_client(&Selector::WinId(swallowed.terminal))?;
+
+        Ok(())
+    }
+}
+
+/// Lays out the screens showing any of `workspaces`.
+fn relayout_showing<X: XConn>(
+    wm: &mut WindowManager<X>,
+    workspaces: &[usize],
+) -> penrose::Result<()> {
+    for screen in 0..wm.n_screens() {
+        let showing = wm
+            .screen(&Selector::Index(screen))
+            .map_or(false, |screen| workspaces.contains(&screen.wix));
+
+        if showing {
+            wm.layout_screen(screen)?;
+        }
+    }
+
+    Ok(())
+}
+
+impl<X: XConn> Hook<X> for SwallowHook {
+    fn client_added_to_workspace(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        id: Xid,
+        workspace_index: usize,
+    ) -> penrose::Result<()> {
+        match self.find_terminal(wm, id) {
+            Some(terminal) => self.swallow(wm, id, workspace_index, terminal),
+            None => Ok(()),
+        }
+    }
+
+    fn remove_client(&mut self, wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        // A swallowed terminal that was closed some other way has nothing to come back to.
+        SWALLOWED_TERMINALS
+            .write()
+            .map_err(swallowed_poisoned)?
+            .remove(&id);
+        self.swallowed
+            .retain(|_window, swallowed| swallowed.terminal != id);
+
+        match self.swallowed.remove(&id) {
+            Some(swallowed) => Self::release(wm, id, &swallowed),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_parent_pid_reads_the_fourth_field() {
+        assert_eq!(
+            parse_parent_pid("1234 (kitty) S 1000 1234 1234 0 -1 4194560"),
+            Some(1000)
+        );
+    }
+
+    #[test]
+    fn parse_parent_pid_skips_brackets_in_the_name() {
+        assert_eq!(
+            parse_parent_pid("1234 (a (weird) name) R 42 1234 1234 0 -1"),
+            Some(42)
+        );
+    }
+
+    #[test]
+    fn parse_parent_pid_needs_a_parent() {
+        assert_eq!(parse_parent_pid("1234 (kitty) S"), None);
+        assert_eq!(parse_parent_pid("1234 kitty S 1000"), None);
+        assert_eq!(parse_parent_pid(""), None);
+    }
+}
@@ -0,0 +1,213 @@
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+use penrose::{
+    core::{xconnection::XConn, Hook},
+    draw::Color,
+    PenroseError, WindowManager, Xid,
+};
+
+use crate::{is_hidden, update_root_name, x_window, Dracula, WindowManagerExt, X_DATA};
+
+/// The urgency flag in `WM_HINTS`.
+const URGENCY_HINT: u32 = 1 << 8;
+
+lazy_static! {
+    /// Clients that want attention, in the order they asked for it.
+    static ref URGENT: RwLock<Vec<Xid>> = RwLock::new(Vec::new());
+
+    /// The focused and unfocused border colours penrose uses, set by the `UrgencyHook`.
+    static ref BORDER_COLOURS: RwLock<Option<(Color, Color)>> = RwLock::new(None);
+}
+
+fn urgent_poisoned<T>(_: T) -> PenroseError {
+    PenroseError::Raw("URGENT RwLock poisoned".into())
+}
+
+/// What to do when a client asks to be activated through `_NET_ACTIVE_WINDOW`.
+#[derive(Clone, Copy)]
+pub enum ActivationPolicy {
+    /// Focus it, switching workspace if needed.
+    Focus,
+    /// Only mark it as urgent.
+    MarkUrgent,
+}
+
+/// Every client that wants attention, the one that has wanted it longest first.
+#[must_use]
+pub fn urgent_clients() -> Vec<Xid> {
+    URGENT
+        .read()
+        .map(|urgent| urgent.clone())
+        .unwrap_or_default()
+}
+
+fn set_border_colour(id: Xid, colour: Color) {
+    X_DATA.conn.send_request(&xcb::x::ChangeWindowAttributes {
+        window: x_window(id),
+        value_list: &[xcb::x::Cw::BorderPixel(colour.rgb_u32())],
+    });
+
+    let _ = X_DATA.conn.flush();
+}
+
+/// Marks a client as wanting attention, or not.
+///
+/// # Errors
+/// Errors if the root window name couldn't be updated.
+pub fn set_urgent<X: XConn>(
+    wm: &mut WindowManager<X>,
+    id: Xid,
+    urgent: bool,
+) -> penrose::Result<()> {
+    // The focused client already has the user's attention.
+    let urgent = urgent && wm.focused_client_id() != Some(id);
+
+    let changed = {
+        let mut urgent_clients = URGENT.write().map_err(urgent_poisoned)?;
+        let was_urgent = urgent_clients.contains(&id);
+
+        if urgent && !was_urgent {
+            urgent_clients.push(id);
+        } else if !urgent {
+            urgent_clients.retain(|other| *other != id);
+        }
+
+        urgent != was_urgent
+    };
+
+    if !changed {
+        return Ok(());
+    }
+
+    if urgent {
+        set_border_colour(id, Color::new_from_hex(Dracula::RED));
+    } else {
+        let colours = *BORDER_COLOURS
+            .read()
+            .map_err(|_| PenroseError::Raw("BORDER_COLOURS RwLock poisoned".into()))?;
+
+        if let Some((focused, unfocused)) = colours {
+            if wm.focused_client_id() == Some(id) {
+                set_border_colour(id, focused);
+            } else {
+                set_border_colour(id, unfocused);
+            }
+        }
+    }
+
+    update_root_name(wm)
+}
+
+/// Updates whether a client is urgent from the flag in its `WM_HINTS`.
+///
+/// # Errors
+/// Errors if the root window name couldn't be updated.
+pub fn update_urgency_from_hints<X: XConn>(
+    wm: &mut WindowManager<X>,
+    id: Xid,
+) -> penrose::Result<()> {
+    let cookie = X_DATA.conn.send_request(&xcb::x::GetProperty {
+        delete: false,
+        window: x_window(id),
+        property: xcb::x::ATOM_WM_HINTS,
+        r#type: xcb::x::ATOM_WM_HINTS,
+        long_offset: 0,
+        long_length: 9,
+    });
+
+    let flags = X_DATA
+        .conn
+        .wait_for_reply(cookie)
+        .ok()
+        // Reading the value as the wrong format panics.
+        .filter(|reply| reply.format() == 32)
+        .and_then(|reply| reply.value::<u32>().first().copied())
+        .unwrap_or(0);
+
+    set_urgent(wm, id, flags & URGENCY_HINT != 0)
+}
+
+/// Handles a client asking to be activated, following the policy.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn handle_activation<X: XConn>(
+    wm: &mut WindowManager<X>,
+    id: Xid,
+    policy: ActivationPolicy,
+) -> penrose::Result<()> {
+    match policy {
+        ActivationPolicy::Focus => wm.focus_client_anywhere(id),
+        ActivationPolicy::MarkUrgent => set_urgent(wm, id, true),
+    }
+}
+
+/// Focuses the client that has wanted attention for the longest.
+///
+/// # Errors
+/// Errors if an inner penrose command fails.
+pub fn focus_urgent<X: XConn>(wm: &mut WindowManager<X>) -> penrose::Result<()> {
+    match urgent_clients().into_iter().find(|id| !is_hidden(*id)) {
+        Some(id) => wm.focus_client_anywhere(id),
+        None => Ok(()),
+    }
+}
+
+/// Listens for `WM_HINTS` changes on new clients and clears urgency when they're focused.
+///
+/// The border colours should be the ones in the config,
+/// so that clients get the right one back when they stop being urgent.
+pub struct UrgencyHook {}
+
+impl UrgencyHook {
+    #[must_use]
+    pub fn new(focused_border: Color, unfocused_border: Color) -> Box<Self> {
+        if let Ok(mut colours) = BORDER_COLOURS.write() {
+            *colours = Some((focused_border, unfocused_border));
+        }
+
+        Box::new(Self {})
+    }
+}
+
+impl<X: XConn> Hook<X> for UrgencyHook {
+    fn new_client(&mut self, wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        // The property changes are picked up by the EwmhHook.
+        X_DATA.conn.send_request(&xcb::x::ChangeWindowAttributes {
+            window: x_window(id),
+            value_list: &[xcb::x::Cw::EventMask(xcb::x::EventMask::PROPERTY_CHANGE)],
+        });
+
+        let _ = X_DATA.conn.flush();
+
+        update_urgency_from_hints(wm, id)
+    }
+
+    fn focus_change(&mut self, wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        set_urgent(wm, id, false)?;
+
+        // Penrose will have redrawn the border of the client that lost focus.
+        for urgent in urgent_clients() {
+            set_border_colour(urgent, Color::new_from_hex(Dracula::RED));
+        }
+
+        Ok(())
+    }
+
+    fn remove_client(&mut self, wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        let removed = {
+            let mut urgent_clients = URGENT.write().map_err(urgent_poisoned)?;
+            let before = urgent_clients.len();
+            urgent_clients.retain(|other| *other != id);
+
+            urgent_clients.len() != before
+        };
+
+        if removed {
+            update_root_name(wm)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,17 +1,75 @@
-use std::{collections::HashMap, sync::RwLock};
+use std::collections::HashMap;
 
-use penrose::{core::data_types::Region, Xid};
+use penrose::{
+    core::{xconnection::XConn, Client},
+    Selector, WindowManager, Xid,
+};
 
-use lazy_static::lazy_static;
+use crate::{is_hidden, PickerQueue, WindowManagerExt, FOCUS_HISTORY};
 
-lazy_static! {
-    pub static ref CLIENT_REGIONS: RwLock<HashMap<Xid, Region>> = RwLock::new(HashMap::new());
+/// Every client on every workspace that isn't hidden, most recently focused first,
+/// along with the line to show for it in the switcher.
+#[must_use]
+pub fn window_switcher_entries<X: XConn>(wm: &WindowManager<X>) -> Vec<(Xid, String)> {
+    let history = FOCUS_HISTORY
+        .read()
+        .map(|history| history.clone())
+        .unwrap_or_default();
+
+    let mut clients = wm
+        .all_clients(&Selector::Any)
+        .into_iter()
+        .filter(|client| !is_hidden(client.id()))
+        .collect::<Vec<_>>();
+
+    // Clients that have never been focused go at the end.
+    clients.sort_by_key(|client| {
+        history
+            .iter()
+            .position(|id| *id == client.id())
+            .unwrap_or(usize::MAX)
+    });
+
+    let mut entries: Vec<(Xid, String)> = vec![];
+    let mut seen = HashMap::<String, usize>::new();
+
+    for client in clients {
+        let client: &Client = client;
+
+        let workspace = wm
+            .workspace(&Selector::Index(client.workspace()))
+            .map_or_else(
+                || client.workspace().to_string(),
+                |ws| ws.name().to_string(),
+            );
+
+        let line = format!("{workspace}: {} - {}", client.wm_class(), client.wm_name());
+
+        // The picker only gives back the line, so they need to be unique.
+        let count = seen.entry(line.clone()).or_insert(0);
+        *count += 1;
+
+        if *count > 1 {
+            entries.push((client.id(), format!("{line} ({count})")));
+        } else {
+            entries.push((client.id(), line));
+        }
+    }
+
+    entries
 }
 
-#[derive(Clone, Copy)]
-pub enum SwitchDirection {
-    Up,
-    Down,
-    Left,
-    Right,
+/// Lists every window on every workspace in the picker,
+/// and focuses the one picked, wherever it is.
+pub fn window_switcher<X: XConn + 'static>(wm: &WindowManager<X>, pickers: &PickerQueue<X>) {
+    let entries = window_switcher_entries(wm);
+
+    let lines = entries
+        .iter()
+        .map(|(_id, line)| line.clone())
+        .collect::<Vec<_>>();
+
+    pickers.pick_index("window", lines, move |wm, index| {
+        wm.focus_client_anywhere(entries[index].0)
+    });
 }
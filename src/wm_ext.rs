@@ -1,125 +1,514 @@
 use std::collections::HashMap;
 
 use penrose::{
-    core::{data_types::Region, ring::Direction, xconnection::XConn},
-    PenroseError, Selector, WindowManager,
+    core::{
+        data_types::Region,
+        ring::{Direction, InsertPoint},
+        xconnection::XConn,
+        Client,
+    },
+    PenroseError, Selector, WindowManager, Xid,
 };
 
-use crate::{SwitchDirection, CLIENT_REGIONS};
-
-const fn region_center(region: &Region) -> (usize, usize) {
-    (
-        (region.x + (region.w / 2)) as usize,
-        (region.y + (region.h / 2)) as usize,
-    )
-}
+use crate::{
+    center_within, grow_floating,
+    layouts::{Split, LAYOUT_SPLITS, RATIO_STEP, SPLIT_RATIOS},
+    move_floating, nearest_in_direction, pointer_position, window_region, SwitchDirection,
+    CLIENT_REGIONS,
+};
 
 pub trait WindowManagerExt {
-    /// Moves the focused window to the next screen in the given direction.
+    /// Screen indices sorted by where they physically are, left to right then top to bottom.
+    fn screens_by_position(&self) -> Vec<usize>;
+
+    /// Moves the focused window to a screen.
+    ///
+    /// If `follow` is set the focus goes with it.
+    ///
+    /// # Errors
+    /// Errors if an inner penrose command fails.
+    fn send_client_to_screen(&mut self, screen: usize, follow: bool) -> penrose::Result<()>;
+
+    /// Moves the focused window to the next screen in the given direction,
+    /// going through the screens in the order they physically are.
     ///
     /// # Errors
     /// Errors if an inner penrose command fails.
-    fn cycle_client_to_screen(&mut self, direction: Direction) -> penrose::Result<()>;
+    fn cycle_client_to_screen(&mut self, direction: Direction, follow: bool)
+        -> penrose::Result<()>;
+
+    /// Moves the focused window to the screen physically next to the active one.
+    ///
+    /// # Errors
+    /// Errors if an inner penrose command fails.
+    fn send_client_in_direction(
+        &mut self,
+        direction: SwitchDirection,
+        follow: bool,
+    ) -> penrose::Result<()>;
 
     /// Switches the focus in a physical direction, instead of in the stack.
     ///
+    /// If there is nothing further in that direction on the current screen,
+    /// the focus moves to the nearest client on the screen next to it.
+    ///
     /// # Errors
     /// Errors if an inner penrose command fails.
     fn switch_focus_in_direction(&mut self, direction: SwitchDirection) -> penrose::Result<()>;
+
+    /// The regions of all tiled clients on currently visible workspaces.
+    ///
+    /// # Errors
+    /// Errors if the `CLIENT_REGIONS` lock is poisoned.
+    fn visible_client_regions(&self) -> penrose::Result<HashMap<Xid, Region>>;
+
+    /// The index of the screen physically next to the active one in the given direction.
+    fn screen_in_direction(&self, direction: SwitchDirection) -> Option<usize>;
+
+    /// The index of the screen the mouse pointer is on.
+    fn screen_under_pointer(&self) -> Option<usize>;
+
+    /// The index of the screen showing the workspace a client is on, if one is.
+    fn screen_showing_client(&self, id: Xid) -> Option<usize>;
+
+    /// Lays out the screen showing a client, if one is.
+    ///
+    /// # Errors
+    /// Errors if an inner penrose command fails.
+    fn layout_client_screen(&mut self, id: Xid) -> penrose::Result<()>;
+
+    /// Focuses a screen without changing which workspace it shows.
+    ///
+    /// # Errors
+    /// Errors if an inner penrose command fails.
+    fn focus_screen(&mut self, index: usize) -> penrose::Result<()>;
+
+    /// Focuses a client on any workspace,
+    /// going to the screen showing it or bringing its workspace onto this screen.
+    ///
+    /// # Errors
+    /// Errors if the client doesn't exist, or if an inner penrose command fails.
+    fn focus_client_anywhere(&mut self, id: Xid) -> penrose::Result<()>;
+
+    /// Moves any client to a workspace without focusing it,
+    /// unlike penrose's `client_to_workspace` which only moves the focused one.
+    ///
+    /// The caller needs to lay out any screens this changes.
+    ///
+    /// # Errors
+    /// Errors if the client or workspace doesn't exist.
+    fn move_client_to_workspace(&mut self, id: Xid, workspace: usize) -> penrose::Result<()>;
+
+    /// Swaps the positions of two clients on the active workspace,
+    /// or swaps which workspaces they're on if they're on different ones.
+    ///
+    /// # Errors
+    /// Errors if an inner penrose command fails.
+    fn swap_clients(&mut self, a: Xid, b: Xid) -> penrose::Result<()>;
+
+    /// Moves the focused window in a physical direction.
+    ///
+    /// Tiled windows swap with their neighbour, or go to the next screen when at the edge.
+    /// Floating windows are moved by a step.
+    ///
+    /// # Errors
+    /// Errors if an inner penrose command fails.
+    fn move_in_direction(&mut self, direction: SwitchDirection) -> penrose::Result<()>;
+
+    /// Moves the edge of the focused window in a direction, making it bigger.
+    ///
+    /// For tiled windows this moves the split on that side,
+    /// for floating windows it resizes the window itself.
+    ///
+    /// # Errors
+    /// Errors if an inner penrose command fails.
+    fn grow_in_direction(&mut self, direction: SwitchDirection) -> penrose::Result<()>;
 }
 
 impl<X: XConn> WindowManagerExt for WindowManager<X> {
-    fn cycle_client_to_screen(&mut self, direction: Direction) -> penrose::Result<()> {
+    fn screens_by_position(&self) -> Vec<usize> {
+        let mut screens = (0..self.n_screens())
+            .filter_map(|index| {
+                self.screen(&Selector::Index(index))
+                    .map(|screen| (index, screen.region(false)))
+            })
+            .collect::<Vec<_>>();
+
+        screens.sort_by_key(|(_index, region)| (region.x, region.y));
+
+        screens.into_iter().map(|(index, _region)| index).collect()
+    }
+
+    fn send_client_to_screen(&mut self, screen: usize, follow: bool) -> penrose::Result<()> {
         let focused_index = match self.focused_client_id() {
             Some(id) => id,
             None => return Ok(()),
         };
 
-        let current_screen = self.active_screen_index();
-
-        match direction {
-            Direction::Forward => {
-                if current_screen == self.n_screens() - 1 {
-                    self.client_to_screen(&Selector::Index(0))?;
-                } else {
-                    self.client_to_screen(&Selector::Index(current_screen + 1))?;
-                }
-            }
-            Direction::Backward => {
-                if current_screen == 0 {
-                    self.client_to_screen(&Selector::Index(self.n_screens() - 1))?;
-                } else {
-                    self.client_to_screen(&Selector::Index(current_screen - 1))?;
-                }
-            }
+        if screen == self.active_screen_index() {
+            return Ok(());
         }
 
-        self.focus_client(&Selector::WinId(focused_index))?;
+        self.client_to_screen(&Selector::Index(screen))?;
+
+        if follow {
+            self.focus_screen(screen)?;
+            self.focus_client(&Selector::WinId(focused_index))?;
+        }
 
         Ok(())
     }
 
+    fn cycle_client_to_screen(
+        &mut self,
+        direction: Direction,
+        follow: bool,
+    ) -> penrose::Result<()> {
+        let screens = self.screens_by_position();
+
+        let current = match screens
+            .iter()
+            .position(|index| *index == self.active_screen_index())
+        {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+
+        let next = match direction {
+            Direction::Forward => screens[(current + 1) % screens.len()],
+            Direction::Backward => screens[(current + screens.len() - 1) % screens.len()],
+        };
+
+        self.send_client_to_screen(next, follow)
+    }
+
+    fn send_client_in_direction(
+        &mut self,
+        direction: SwitchDirection,
+        follow: bool,
+    ) -> penrose::Result<()> {
+        match self.screen_in_direction(direction) {
+            Some(screen) => self.send_client_to_screen(screen, follow),
+            None => Ok(()),
+        }
+    }
+
     fn switch_focus_in_direction(&mut self, direction: SwitchDirection) -> penrose::Result<()> {
-        const fn dst_in_one_dir(a: usize, b: usize) -> usize {
-            if a > b {
-                std::usize::MAX
-            } else {
-                b - a
-            }
+        let screen_region = self
+            .screen(&Selector::Index(self.active_screen_index()))
+            .ok_or(PenroseError::Raw("No active screen".to_string()))?
+            .region(false);
+
+        let regions = self.visible_client_regions()?;
+
+        // With nothing focused, act as if the whole screen was.
+        let current_client = self.focused_client_id();
+        let current = current_client
+            .and_then(|id| regions.get(&id).copied().or_else(|| window_region(id)))
+            .unwrap_or(screen_region);
+
+        let others = regions
+            .iter()
+            .filter(|(id, _region)| Some(**id) != current_client)
+            .map(|(id, region)| (*id, *region))
+            .collect::<Vec<_>>();
+
+        let on_screen = others
+            .iter()
+            .copied()
+            .filter(|(_id, region)| center_within(region, &screen_region));
+
+        if let Some((id, _region)) = nearest_in_direction(&current, on_screen, direction) {
+            self.focus_client(&Selector::WinId(id))?;
+            return Ok(());
         }
 
-        let current_client = match self.focused_client_id() {
-            Some(id) => id,
+        let next_screen = match self.screen_in_direction(direction) {
+            Some(index) => index,
             None => return Ok(()),
         };
 
+        let next_screen_region = self
+            .screen(&Selector::Index(next_screen))
+            .ok_or(PenroseError::Raw("Screen disappeared".to_string()))?
+            .region(false);
+
+        let on_next_screen = others
+            .into_iter()
+            .filter(|(_id, region)| center_within(region, &next_screen_region));
+
+        match nearest_in_direction(&current, on_next_screen, direction) {
+            Some((id, _region)) => {
+                self.focus_client(&Selector::WinId(id))?;
+            }
+            // The workspace over there is empty, so just go to it.
+            None => self.focus_screen(next_screen)?,
+        }
+
+        Ok(())
+    }
+
+    fn visible_client_regions(&self) -> penrose::Result<HashMap<Xid, Region>> {
+        let visible_workspaces = self.focused_workspaces();
+
+        let visible_clients = self
+            .all_clients(&Selector::Condition(&|client: &Client| {
+                visible_workspaces.contains(&client.workspace())
+            }))
+            .into_iter()
+            .map(|client| client.id())
+            .collect::<Vec<_>>();
+
         let regions = CLIENT_REGIONS
             .read()
             .map_err(|_| PenroseError::Raw("CLIENT_REGIONS RwLock poisoned".into()))?
-            .clone();
+            .iter()
+            .filter(|(id, _region)| visible_clients.contains(id))
+            .map(|(id, region)| (*id, *region))
+            .collect();
 
-        let current = region_center(&regions[&current_client]);
+        Ok(regions)
+    }
 
-        let positions = regions
-            .into_iter()
-            .filter(|(id, _region)| *id != current_client)
-            .map(|(id, region)| (id, region_center(&region)))
-            .collect::<HashMap<_, _>>();
+    fn screen_in_direction(&self, direction: SwitchDirection) -> Option<usize> {
+        let current_screen = self.active_screen_index();
 
-        let next = match direction {
-            SwitchDirection::Up => {
-                let on_same_x = positions
-                    .iter()
-                    .filter(|(_id, pos)| pos.0 == current.0)
-                    .collect::<HashMap<_, _>>();
-
-                if on_same_x.is_empty() {
-                    todo!()
-                } else {
-                    let _ = on_same_x
-                        .into_iter()
-                        .min_by(|(_, a_c), (_, b_c)| {
-                            dst_in_one_dir(a_c.1, current.1).cmp(&dst_in_one_dir(b_c.1, current.1))
-                        })
-                        .ok_or_else(|| unreachable!());
-                    todo!()
+        let current = self.screen(&Selector::Index(current_screen))?.region(false);
+
+        let others = (0..self.n_screens())
+            .filter(|index| *index != current_screen)
+            .filter_map(|index| {
+                self.screen(&Selector::Index(index))
+                    .map(|screen| (index, screen.region(false)))
+            });
+
+        nearest_in_direction(&current, others, direction).map(|(index, _region)| index)
+    }
+
+    fn screen_under_pointer(&self) -> Option<usize> {
+        let pointer = pointer_position()?;
+
+        (0..self.n_screens()).find(|index| {
+            self.screen(&Selector::Index(*index))
+                .map_or(false, |screen| screen.contains(pointer))
+        })
+    }
+
+    fn screen_showing_client(&self, id: Xid) -> Option<usize> {
+        let workspace = self.client(&Selector::WinId(id))?.workspace();
+
+        (0..self.n_screens()).find(|index| {
+            self.screen(&Selector::Index(*index))
+                .map_or(false, |screen| screen.wix == workspace)
+        })
+    }
+
+    fn layout_client_screen(&mut self, id: Xid) -> penrose::Result<()> {
+        match self.screen_showing_client(id) {
+            Some(screen) => self.layout_screen(screen),
+            None => Ok(()),
+        }
+    }
+
+    fn focus_screen(&mut self, index: usize) -> penrose::Result<()> {
+        // Penrose can only cycle through screens, and focusing a workspace on
+        // another screen would pull it onto this one.
+        for _ in 0..self.n_screens() {
+            if self.active_screen_index() == index {
+                break;
+            }
+
+            self.cycle_screen(Direction::Forward)?;
+        }
+
+        Ok(())
+    }
+
+    fn focus_client_anywhere(&mut self, id: Xid) -> penrose::Result<()> {
+        let workspace = self
+            .client(&Selector::WinId(id))
+            .ok_or(PenroseError::Raw(format!("No client with id {id}")))?
+            .workspace();
+
+        match self.screen_showing_client(id) {
+            Some(screen) => self.focus_screen(screen)?,
+            None => self.focus_workspace(&Selector::Index(workspace))?,
+        }
+
+        self.focus_client(&Selector::WinId(id))?;
+
+        Ok(())
+    }
+
+    fn move_client_to_workspace(&mut self, id: Xid, workspace: usize) -> penrose::Result<()> {
+        let current = self
+            .client(&Selector::WinId(id))
+            .ok_or(PenroseError::Raw(format!("No client with id {id}")))?
+            .workspace();
+
+        if current == workspace {
+            return Ok(());
+        }
+
+        self.workspace_mut(&Selector::Index(workspace))
+            .ok_or(PenroseError::Raw(format!("No workspace {workspace}")))?
+            .add_client(id, &InsertPoint::Last)?;
+
+        if let Some(ws) = self.workspace_mut(&Selector::Index(current)) {
+            ws.remove_client(id);
+        }
+
+        if let Some(client) = self.client_mut(&Selector::WinId(id)) {
+            client.set_workspace(workspace);
+        }
+
+        Ok(())
+    }
+
+    fn swap_clients(&mut self, a: Xid, b: Xid) -> penrose::Result<()> {
+        let workspace_of = |id| {
+            self.client(&Selector::WinId(id))
+                .map(|client| client.workspace())
+        };
+
+        if let (Some(a_workspace), Some(b_workspace)) = (workspace_of(a), workspace_of(b)) {
+            if a_workspace != b_workspace {
+                let index_in = |wm: &Self, id: Xid, workspace: usize| {
+                    wm.workspace(&Selector::Index(workspace))
+                        .and_then(|ws| ws.client_ids().iter().position(|other| *other == id))
+                        .unwrap_or_default()
+                };
+
+                // Each takes the other's place in its stack.
+                let a_index = index_in(self, a, a_workspace);
+                let b_index = index_in(self, b, b_workspace);
+
+                for (id, from, to, index) in [
+                    (a, a_workspace, b_workspace, b_index),
+                    (b, b_workspace, a_workspace, a_index),
+                ] {
+                    if let Some(ws) = self.workspace_mut(&Selector::Index(from)) {
+                        ws.remove_client(id);
+                    }
+
+                    self.workspace_mut(&Selector::Index(to))
+                        .ok_or(PenroseError::Raw(format!("No workspace {to}")))?
+                        .add_client(id, &InsertPoint::Index(index))?;
+
+                    if let Some(client) = self.client_mut(&Selector::WinId(id)) {
+                        client.set_workspace(to);
+                    }
                 }
+
+                for screen in 0..self.n_screens() {
+                    self.layout_screen(screen)?;
+                }
+
+                return self.focus_client_anywhere(a);
             }
-            SwitchDirection::Down => positions.iter().max_by(|(_, a_center), (_, b_center)| {
-                (a_center.1 - current.1).cmp(&(b_center.1 - current.1))
-            }),
-            SwitchDirection::Left => positions.iter().min_by(|(_, a_center), (_, b_center)| {
-                (a_center.0 - current.0).cmp(&(b_center.0 - current.0))
-            }),
-            SwitchDirection::Right => positions.iter().max_by(|(_, a_center), (_, b_center)| {
-                (a_center.0 - current.0).cmp(&(b_center.0 - current.0))
-            }),
         }
-        .map(|(&id, &pos)| (id, pos))
-        .expect("No windows");
 
-        self.focus_client(&Selector::WinId(next.0))?;
+        let ids = self.active_workspace().client_ids();
+
+        let (first, second) = match (
+            ids.iter().position(|id| *id == a),
+            ids.iter().position(|id| *id == b),
+        ) {
+            (Some(a_index), Some(b_index)) if a_index != b_index => {
+                (a_index.min(b_index), a_index.max(b_index))
+            }
+            _ => return Ok(()),
+        };
+
+        // Penrose can only drag the focused client one place at a time,
+        // and dragging past either end of the stack rotates it,
+        // so only ever drag between the two positions.
+        self.focus_client(&Selector::WinId(ids[first]))?;
+        for _ in first..second {
+            self.drag_client(Direction::Forward)?;
+        }
+
+        // The other client has been pushed back a place by that.
+        self.focus_client(&Selector::WinId(ids[second]))?;
+        for _ in first + 1..second {
+            self.drag_client(Direction::Backward)?;
+        }
+
+        self.focus_client(&Selector::WinId(a))?;
 
         Ok(())
     }
+
+    fn move_in_direction(&mut self, direction: SwitchDirection) -> penrose::Result<()> {
+        let current_client = match self.focused_client_id() {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        let regions = self.visible_client_regions()?;
+
+        let current = match regions.get(&current_client) {
+            Some(region) => *region,
+            None => return move_floating(self, current_client, direction),
+        };
+
+        let screen_region = self
+            .screen(&Selector::Index(self.active_screen_index()))
+            .ok_or(PenroseError::Raw("No active screen".to_string()))?
+            .region(false);
+
+        let on_screen = regions
+            .into_iter()
+            .filter(|(id, region)| *id != current_client && center_within(region, &screen_region));
+
+        if let Some((id, _region)) = nearest_in_direction(&current, on_screen, direction) {
+            return self.swap_clients(current_client, id);
+        }
+
+        self.send_client_in_direction(direction, true)
+    }
+
+    fn grow_in_direction(&mut self, direction: SwitchDirection) -> penrose::Result<()> {
+        let current_client = match self.focused_client_id() {
+            Some(id) => id,
+            None => return Ok(()),
+        };
+
+        let current = match self.visible_client_regions()?.get(&current_client) {
+            Some(region) => *region,
+            None => return grow_floating(self, current_client, direction),
+        };
+
+        let (split_type, edge, change) = match direction {
+            SwitchDirection::Left => (Split::Vertical, current.x, -RATIO_STEP),
+            SwitchDirection::Right => (Split::Vertical, current.x + current.w, RATIO_STEP),
+            SwitchDirection::Up => (Split::Horizontal, current.y, -RATIO_STEP),
+            SwitchDirection::Down => (Split::Horizontal, current.y + current.h, RATIO_STEP),
+        };
+
+        // The innermost split along that edge is the one this window belongs to.
+        let split = LAYOUT_SPLITS
+            .read()
+            .map_err(|_| PenroseError::Raw("LAYOUT_SPLITS RwLock poisoned".into()))?
+            .iter()
+            .filter(|split| {
+                split.split == split_type
+                    && split.position() == edge
+                    && center_within(&current, &split.region)
+            })
+            .min_by_key(|split| split.region.w * split.region.h)
+            .copied();
+
+        let split = match split {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+
+        SPLIT_RATIOS
+            .write()
+            .map_err(|_| PenroseError::Raw("SPLIT_RATIOS RwLock poisoned".into()))?
+            .insert(split.key, (split.ratio + change).clamp(0.1, 0.9));
+
+        self.layout_screen(self.active_screen_index())
+    }
 }
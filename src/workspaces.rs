@@ -0,0 +1,490 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use penrose::{
+    core::{ring::Direction, workspace::Workspace, xconnection::XConn, Hook},
+    PenroseError, Selector, WindowManager, Xid,
+};
+
+use crate::{intern_atom, layouts, output_regions, PickerQueue, WindowManagerExt, X_DATA};
+
+/// What focusing a workspace that is already shown on another screen does.
+#[derive(Clone, Copy)]
+pub enum WorkspaceFocusPolicy {
+    /// Pull it onto this screen, putting this screen's workspace where it was.
+    Swap,
+    /// Leave it where it is and focus that screen.
+    Warp,
+}
+
+struct WorkspaceState {
+    policy: WorkspaceFocusPolicy,
+    /// Workspace indexes and the names of the `RandR` outputs they're kept on.
+    pins: HashMap<usize, String>,
+    /// How many workspaces there were at startup, which are never removed.
+    fixed: usize,
+    /// The workspace on each screen when the history was last updated.
+    shown: Vec<usize>,
+    /// The workspace each screen showed before its current one.
+    previous: HashMap<usize, usize>,
+}
+
+/// Switches workspaces following a `WorkspaceFocusPolicy`,
+/// keeping pinned workspaces on their outputs
+/// and remembering the previous workspace of each screen.
+///
+/// The hook from `get_hook` must be added to the hooks.
+#[derive(Clone)]
+pub struct WorkspaceManager {
+    state: Rc<RefCell<WorkspaceState>>,
+}
+
+impl WorkspaceManager {
+    #[must_use]
+    pub fn new(policy: WorkspaceFocusPolicy) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(WorkspaceState {
+                policy,
+                pins: HashMap::new(),
+                fixed: usize::MAX,
+                shown: vec![],
+                previous: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Keeps the workspace at `index` on the `RandR` output called `output`, like "DP-1".
+    ///
+    /// If that output isn't connected the workspace can go anywhere.
+    #[must_use]
+    pub fn pin(self, index: usize, output: impl Into<String>) -> Self {
+        self.state.borrow_mut().pins.insert(index, output.into());
+        self
+    }
+
+    #[must_use]
+    pub fn get_hook(&self) -> Box<WorkspaceHook> {
+        Box::new(WorkspaceHook {
+            manager: self.clone(),
+        })
+    }
+
+    /// The screen showing the output a workspace is pinned to.
+    fn pinned_screen<X: XConn>(&self, wm: &WindowManager<X>, index: usize) -> Option<usize> {
+        let state = self.state.borrow();
+        let output = state.pins.get(&index)?;
+
+        let (_name, region) = output_regions()
+            .into_iter()
+            .find(|(name, _region)| name == output)?;
+
+        (0..wm.n_screens()).find(|screen| {
+            wm.screen(&Selector::Index(*screen))
+                .map_or(false, |screen| {
+                    let screen_region = screen.region(false);
+                    screen_region.x == region.x && screen_region.y == region.y
+                })
+        })
+    }
+
+    fn is_pinned(&self, index: usize) -> bool {
+        self.state.borrow().pins.contains_key(&index)
+    }
+
+    /// The screen the workspace at `index` is shown on, if it is.
+    fn visible_on<X: XConn>(wm: &WindowManager<X>, index: usize) -> Option<usize> {
+        (0..wm.n_screens()).find(|screen| {
+            wm.screen(&Selector::Index(*screen))
+                .map_or(false, |screen| screen.wix == index)
+        })
+    }
+
+    /// Notices which workspaces each screen has moved on from since it was last called.
+    ///
+    /// Hooks don't run for workspace changes made inside other hooks,
+    /// so this is also called before the history is used.
+    fn update_history<X: XConn>(&self, wm: &WindowManager<X>) {
+        let shown = (0..wm.n_screens())
+            .filter_map(|screen| wm.screen(&Selector::Index(screen)).map(|screen| screen.wix))
+            .collect::<Vec<_>>();
+
+        let mut state = self.state.borrow_mut();
+        let WorkspaceState {
+            shown: last_shown,
+            previous,
+            ..
+        } = &mut *state;
+
+        for (screen, (last, current)) in last_shown.iter().zip(&shown).enumerate() {
+            if last != current {
+                previous.insert(screen, *last);
+            }
+        }
+
+        *last_shown = shown;
+    }
+
+    /// Focuses the workspace at `index`,
+    /// or the previous workspace of the focused screen if it's already focused.
+    ///
+    /// Pinned workspaces are shown on their own output.
+    /// Otherwise a workspace shown on another screen is handled by the policy,
+    /// except that a pinned workspace is never swapped off its output.
+    ///
+    /// # Errors
+    /// Errors if an inner penrose command fails.
+    pub fn focus_workspace<X: XConn>(
+        &self,
+        wm: &mut WindowManager<X>,
+        index: usize,
+    ) -> penrose::Result<()> {
+        self.update_history(wm);
+
+        if Self::visible_on(wm, index) == Some(wm.active_screen_index()) {
+            return self.previous_workspace(wm);
+        }
+
+        self.show_workspace(wm, index)
+    }
+
+    /// Goes back to the workspace the focused screen showed before this one.
+    ///
+    /// # Errors
+    /// Errors if an inner penrose command fails.
+    pub fn previous_workspace<X: XConn>(&self, wm: &mut WindowManager<X>) -> penrose::Result<()> {
+        self.update_history(wm);
+
+        let previous = self
+            .state
+            .borrow()
+            .previous
+            .get(&wm.active_screen_index())
+            .copied();
+
+        match previous {
+            // It might have been removed since.
+            Some(index) if index < workspace_names(wm).len() => self.show_workspace(wm, index),
+            _ => Ok(()),
+        }
+    }
+
+    fn show_workspace<X: XConn>(
+        &self,
+        wm: &mut WindowManager<X>,
+        index: usize,
+    ) -> penrose::Result<()> {
+        if let Some(screen) = self.pinned_screen(wm, index) {
+            wm.focus_screen(screen)?;
+        }
+
+        let active_workspace = wm
+            .screen(&Selector::Index(wm.active_screen_index()))
+            .map(|screen| screen.wix);
+
+        let policy = match active_workspace {
+            Some(active) if self.is_pinned(active) => WorkspaceFocusPolicy::Warp,
+            _ => self.state.borrow().policy,
+        };
+
+        match (Self::visible_on(wm, index), policy) {
+            (Some(screen), WorkspaceFocusPolicy::Warp) => wm.focus_screen(screen),
+            _ => wm.focus_workspace(&Selector::Index(index)),
+        }
+    }
+
+    /// Swaps the workspace on the focused screen with the one on the next screen
+    /// to the right, or to the left going backwards.
+    ///
+    /// Does nothing if either workspace is pinned.
+    ///
+    /// # Errors
+    /// Errors if an inner penrose command fails.
+    pub fn swap_visible_workspaces<X: XConn>(
+        &self,
+        wm: &mut WindowManager<X>,
+        direction: Direction,
+    ) -> penrose::Result<()> {
+        let screens = wm.screens_by_position();
+
+        let position = match screens
+            .iter()
+            .position(|screen| *screen == wm.active_screen_index())
+        {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+
+        let other_position = match direction {
+            Direction::Forward => (position + 1) % screens.len(),
+            Direction::Backward => (position + screens.len() - 1) % screens.len(),
+        };
+
+        if other_position == position {
+            return Ok(());
+        }
+
+        let workspaces = [screens[position], screens[other_position]]
+            .map(|screen| wm.screen(&Selector::Index(screen)).map(|screen| screen.wix));
+
+        match workspaces {
+            [Some(active), Some(other)] if !self.is_pinned(active) && !self.is_pinned(other) => {
+                // Penrose swaps workspaces when focusing one on another screen.
+                wm.focus_workspace(&Selector::Index(other))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Adds a workspace after the others, returning its index.
+    ///
+    /// # Errors
+    /// Errors if an inner penrose command fails.
+    pub fn push_workspace<X: XConn>(
+        &self,
+        wm: &mut WindowManager<X>,
+        name: impl Into<String>,
+    ) -> penrose::Result<usize> {
+        wm.push_workspace(Workspace::new(name, layouts::layouts()))?;
+        publish_workspace_names(wm);
+
+        Ok(workspace_names(wm).len() - 1)
+    }
+
+    /// Asks for a name, then adds a workspace with it and focuses it.
+    pub fn add_workspace_picker<X: XConn + 'static>(&self, pickers: &PickerQueue<X>) {
+        let manager = self.clone();
+        pickers.pick("new workspace", vec![], move |wm, name| {
+            let index = manager.push_workspace(wm, name)?;
+            manager.show_workspace(wm, index)
+        });
+    }
+
+    /// Gives the workspace at `index` a new name.
+    ///
+    /// # Errors
+    /// Errors if there is no workspace at `index`.
+    pub fn rename_workspace<X: XConn>(
+        &self,
+        wm: &mut WindowManager<X>,
+        index: usize,
+        name: impl Into<String>,
+    ) -> penrose::Result<()> {
+        wm.workspace_mut(&Selector::Index(index))
+            .ok_or(PenroseError::Raw(format!("No workspace at {index}")))?
+            .set_name(name);
+
+        publish_workspace_names(wm);
+
+        Ok(())
+    }
+
+    /// Asks for a new name for the focused workspace.
+    pub fn rename_workspace_picker<X: XConn + 'static>(
+        &self,
+        wm: &WindowManager<X>,
+        pickers: &PickerQueue<X>,
+    ) {
+        let index = match wm.screen(&Selector::Index(wm.active_screen_index())) {
+            Some(screen) => screen.wix,
+            None => return,
+        };
+
+        let current = workspace_names(wm).get(index).cloned().unwrap_or_default();
+
+        let manager = self.clone();
+        pickers.pick("rename workspace", vec![current], move |wm, name| {
+            manager.rename_workspace(wm, index, name)
+        });
+    }
+
+    /// Moves the focused window to a workspace named after its class,
+    /// adding that workspace if needed, and follows it there.
+    ///
+    /// # Errors
+    /// Errors if an inner penrose command fails.
+    pub fn focused_to_app_workspace<X: XConn>(
+        &self,
+        wm: &mut WindowManager<X>,
+    ) -> penrose::Result<()> {
+        let (id, class) = match wm.client(&Selector::Focused) {
+            Some(client) => (client.id(), client.wm_class().to_string()),
+            None => return Ok(()),
+        };
+
+        let index = match workspace_names(wm).iter().position(|name| *name == class) {
+            Some(index) => index,
+            None => self.push_workspace(wm, class)?,
+        };
+
+        wm.move_client_to_workspace(id, index)?;
+        self.show_workspace(wm, index)?;
+        wm.focus_client(&Selector::WinId(id))?;
+
+        Ok(())
+    }
+
+    /// Removes the focused workspace if it's the last one, was added at runtime and is empty,
+    /// moving to the workspace before it.
+    ///
+    /// Only the last workspace can be removed, as penrose doesn't renumber the others.
+    ///
+    /// # Errors
+    /// Errors if an inner penrose command fails.
+    pub fn remove_focused_workspace<X: XConn>(
+        &self,
+        wm: &mut WindowManager<X>,
+    ) -> penrose::Result<()> {
+        let index = match wm.screen(&Selector::Index(wm.active_screen_index())) {
+            Some(screen) => screen.wix,
+            None => return Ok(()),
+        };
+
+        let previous = match index.checked_sub(1) {
+            Some(previous) => previous,
+            None => return Ok(()),
+        };
+
+        if index + 1 != workspace_names(wm).len() || !self.is_removable(wm, index, None) {
+            return Ok(());
+        }
+
+        self.show_workspace(wm, previous)?;
+        self.remove_empty_workspaces(wm, None)
+    }
+
+    /// Whether a workspace was added at runtime and is empty, apart from `closing`.
+    fn is_removable<X: XConn>(
+        &self,
+        wm: &WindowManager<X>,
+        index: usize,
+        closing: Option<Xid>,
+    ) -> bool {
+        index >= self.state.borrow().fixed
+            && wm
+                .workspace(&Selector::Index(index))
+                .map_or(false, |workspace| {
+                    workspace.client_ids().iter().all(|id| Some(*id) == closing)
+                })
+    }
+
+    /// Removes empty workspaces added at runtime from the end, until one is in use.
+    ///
+    /// `closing` is a client that's closing but might not be out of its workspace yet.
+    fn remove_empty_workspaces<X: XConn>(
+        &self,
+        wm: &mut WindowManager<X>,
+        closing: Option<Xid>,
+    ) -> penrose::Result<()> {
+        let mut removed = false;
+
+        loop {
+            let last = workspace_names(wm).len().saturating_sub(1);
+
+            if !self.is_removable(wm, last, closing) || Self::visible_on(wm, last).is_some() {
+                break;
+            }
+
+            wm.remove_workspace(&Selector::Index(last))?;
+            removed = true;
+        }
+
+        if removed {
+            publish_workspace_names(wm);
+        }
+
+        Ok(())
+    }
+}
+
+/// The names of every workspace, in order.
+#[must_use]
+pub fn workspace_names<X: XConn>(wm: &WindowManager<X>) -> Vec<String> {
+    wm.all_workspaces(&Selector::Any)
+        .into_iter()
+        .map(|workspace| workspace.name().to_string())
+        .collect()
+}
+
+/// Sets `_NET_DESKTOP_NAMES` and `_NET_NUMBER_OF_DESKTOPS` on the root window,
+/// so that bars and pagers know about workspaces added or renamed at runtime.
+pub fn publish_workspace_names<X: XConn>(wm: &WindowManager<X>) {
+    let names = workspace_names(wm);
+
+    if let (Some(desktop_names), Some(utf8_string)) = (
+        intern_atom("_NET_DESKTOP_NAMES"),
+        intern_atom("UTF8_STRING"),
+    ) {
+        // The names are each followed by a null byte.
+        let data = names
+            .iter()
+            .flat_map(|name| name.bytes().chain(std::iter::once(0)))
+            .collect::<Vec<_>>();
+
+        X_DATA.conn.send_request(&xcb::x::ChangeProperty {
+            mode: xcb::x::PropMode::Replace,
+            window: X_DATA.root,
+            property: desktop_names,
+            r#type: utf8_string,
+            data: &data,
+        });
+    }
+
+    if let Some(number_of_desktops) = intern_atom("_NET_NUMBER_OF_DESKTOPS") {
+        X_DATA.conn.send_request(&xcb::x::ChangeProperty {
+            mode: xcb::x::PropMode::Replace,
+            window: X_DATA.root,
+            property: number_of_desktops,
+            r#type: xcb::x::ATOM_CARDINAL,
+            data: &[names.len() as u32],
+        });
+    }
+
+    let _ = X_DATA.conn.flush();
+}
+
+pub struct WorkspaceHook {
+    manager: WorkspaceManager,
+}
+
+impl<X: XConn> Hook<X> for WorkspaceHook {
+    fn startup(&mut self, wm: &mut WindowManager<X>) -> penrose::Result<()> {
+        self.manager.state.borrow_mut().fixed = workspace_names(wm).len();
+        publish_workspace_names(wm);
+
+        let pinned = self
+            .manager
+            .state
+            .borrow()
+            .pins
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        let active_screen = wm.active_screen_index();
+
+        // Start with every pinned workspace on its output.
+        for index in pinned {
+            if let Some(screen) = self.manager.pinned_screen(wm, index) {
+                wm.focus_screen(screen)?;
+                wm.focus_workspace(&Selector::Index(index))?;
+            }
+        }
+
+        wm.focus_screen(active_screen)?;
+        self.manager.update_history(wm);
+
+        Ok(())
+    }
+
+    fn workspace_change(
+        &mut self,
+        wm: &mut WindowManager<X>,
+        _previous_workspace: usize,
+        _new_workspace: usize,
+    ) -> penrose::Result<()> {
+        self.manager.update_history(wm);
+        self.manager.remove_empty_workspaces(wm, None)
+    }
+
+    fn remove_client(&mut self, wm: &mut WindowManager<X>, id: Xid) -> penrose::Result<()> {
+        self.manager.remove_empty_workspaces(wm, Some(id))
+    }
+}
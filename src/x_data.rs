@@ -1,4 +1,9 @@
 use lazy_static::lazy_static;
+use penrose::{
+    core::data_types::{Point, Region},
+    Xid,
+};
+use xcb::{Xid as _, XidNew};
 
 pub struct XData {
     pub conn: xcb::Connection,
@@ -8,7 +13,9 @@ pub struct XData {
 lazy_static! {
     pub static ref X_DATA: XData = {
         // Here screen does not relate to monitors, but the virtual screen made up of all monitors.
-        let (conn, screen_num) = xcb::Connection::connect(None).unwrap();
+        // RandR is used to find the names of the monitors.
+        let (conn, screen_num) =
+            xcb::Connection::connect_with_extensions(None, &[xcb::Extension::RandR], &[]).unwrap();
 
         let setup = conn.get_setup();
         let screen = setup.roots().nth(screen_num.try_into().expect("X screen number was negative")).unwrap();
@@ -18,3 +25,341 @@ lazy_static! {
         XData { conn, root }
     };
 }
+
+/// Converts a penrose window id into an xcb window.
+#[must_use]
+pub fn x_window(id: Xid) -> xcb::x::Window {
+    // Penrose only hands out ids of windows that exist, so this is fine.
+    unsafe { xcb::x::Window::new(id) }
+}
+
+/// Asks the X server where a window currently is, including its border.
+///
+/// Used for windows that aren't tiled, so aren't in `CLIENT_REGIONS`.
+#[must_use]
+pub fn window_region(id: Xid) -> Option<Region> {
+    let cookie = X_DATA.conn.send_request(&xcb::x::GetGeometry {
+        drawable: xcb::x::Drawable::Window(x_window(id)),
+    });
+
+    let reply: xcb::x::GetGeometryReply = X_DATA.conn.wait_for_reply(cookie).ok()?;
+
+    // Penrose positions clients by their outer size, so match that here.
+    let border = u32::from(reply.border_width()) * 2;
+
+    Some(Region::new(
+        reply.x().try_into().ok()?,
+        reply.y().try_into().ok()?,
+        u32::from(reply.width()) + border,
+        u32::from(reply.height()) + border,
+    ))
+}
+
+/// Looks up an atom by name, creating it if it doesn't exist yet.
+#[must_use]
+pub fn intern_atom(name: &str) -> Option<xcb::x::Atom> {
+    let cookie = X_DATA.conn.send_request(&xcb::x::InternAtom {
+        only_if_exists: false,
+        name: name.as_bytes(),
+    });
+
+    X_DATA
+        .conn
+        .wait_for_reply(cookie)
+        .ok()
+        .map(|reply| reply.atom())
+}
+
+/// Sends a message to the root window that penrose will ignore,
+/// so that hooks get a chance to run after something happens off the main thread.
+pub fn wake_wm() {
+    let atom = match intern_atom("_PENROSE_ARLO_WAKE") {
+        Some(atom) => atom,
+        None => return,
+    };
+
+    let event = xcb::x::ClientMessageEvent::new(
+        X_DATA.root,
+        atom,
+        xcb::x::ClientMessageData::Data32([0; 5]),
+    );
+
+    X_DATA.conn.send_request(&xcb::x::SendEvent {
+        propagate: false,
+        destination: xcb::x::SendEventDest::Window(X_DATA.root),
+        event_mask: xcb::x::EventMask::SUBSTRUCTURE_REDIRECT
+            | xcb::x::EventMask::SUBSTRUCTURE_NOTIFY,
+        event: &event,
+    });
+
+    let _ = X_DATA.conn.flush();
+}
+
+/// Puts a window above all the others.
+pub fn raise_window(id: Xid) {
+    X_DATA.conn.send_request(&xcb::x::ConfigureWindow {
+        window: x_window(id),
+        value_list: &[xcb::x::ConfigWindow::StackMode(xcb::x::StackMode::Above)],
+    });
+
+    let _ = X_DATA.conn.flush();
+}
+
+/// Where the mouse pointer is on the root window.
+#[must_use]
+pub fn pointer_position() -> Option<Point> {
+    // The xcb library is quite weird, so rust analyzer cannot infer the type of the returned value.
+    let cookie: xcb::x::QueryPointerCookie = X_DATA.conn.send_request(&xcb::x::QueryPointer {
+        window: X_DATA.root,
+    });
+
+    let reply: xcb::x::QueryPointerReply = X_DATA.conn.wait_for_reply(cookie).ok()?;
+
+    Some(Point::new(
+        reply.root_x().try_into().ok()?,
+        reply.root_y().try_into().ok()?,
+    ))
+}
+
+/// Reads a property that holds a list of atoms, like `_NET_WM_STATE`.
+#[must_use]
+pub fn get_atom_property(id: Xid, property: &str) -> Vec<xcb::x::Atom> {
+    let property = match intern_atom(property) {
+        Some(atom) => atom,
+        None => return vec![],
+    };
+
+    let cookie = X_DATA.conn.send_request(&xcb::x::GetProperty {
+        delete: false,
+        window: x_window(id),
+        property,
+        r#type: xcb::x::ATOM_ATOM,
+        long_offset: 0,
+        long_length: 1024,
+    });
+
+    X_DATA
+        .conn
+        .wait_for_reply(cookie)
+        .map(|reply| reply.value::<xcb::x::Atom>().to_vec())
+        .unwrap_or_default()
+}
+
+/// Replaces a property that holds a list of atoms.
+pub fn set_atom_property(id: Xid, property: &str, atoms: &[xcb::x::Atom]) {
+    let property = match intern_atom(property) {
+        Some(atom) => atom,
+        None => return,
+    };
+
+    X_DATA.conn.send_request(&xcb::x::ChangeProperty {
+        mode: xcb::x::PropMode::Replace,
+        window: x_window(id),
+        property,
+        r#type: xcb::x::ATOM_ATOM,
+        data: atoms,
+    });
+
+    let _ = X_DATA.conn.flush();
+}
+
+/// Adds a state like `_NET_WM_STATE_FULLSCREEN` to a window's `_NET_WM_STATE`.
+pub fn add_net_wm_state(id: Xid, state: &str) {
+    let state = match intern_atom(state) {
+        Some(atom) => atom,
+        None => return,
+    };
+
+    let mut states = get_atom_property(id, "_NET_WM_STATE");
+
+    if !states.contains(&state) {
+        states.push(state);
+        set_atom_property(id, "_NET_WM_STATE", &states);
+    }
+}
+
+/// Removes a state like `_NET_WM_STATE_FULLSCREEN` from a window's `_NET_WM_STATE`.
+pub fn remove_net_wm_state(id: Xid, state: &str) {
+    let state = match intern_atom(state) {
+        Some(atom) => atom,
+        None => return,
+    };
+
+    let mut states = get_atom_property(id, "_NET_WM_STATE");
+
+    if states.contains(&state) {
+        states.retain(|other| *other != state);
+        set_atom_property(id, "_NET_WM_STATE", &states);
+    }
+}
+
+/// Changes a window's geometry, border or stacking directly,
+/// without penrose adjusting it for borders and gaps.
+pub fn configure_window(id: Xid, value_list: &[xcb::x::ConfigWindow]) {
+    X_DATA.conn.send_request(&xcb::x::ConfigureWindow {
+        window: x_window(id),
+        value_list,
+    });
+
+    let _ = X_DATA.conn.flush();
+}
+
+/// Reads a text property, like `WM_WINDOW_ROLE`.
+#[must_use]
+pub fn get_string_property(id: Xid, property: &str) -> Option<String> {
+    let property = intern_atom(property)?;
+
+    let cookie = X_DATA.conn.send_request(&xcb::x::GetProperty {
+        delete: false,
+        window: x_window(id),
+        property,
+        r#type: xcb::x::ATOM_ANY,
+        long_offset: 0,
+        long_length: 1024,
+    });
+
+    let reply = X_DATA.conn.wait_for_reply(cookie).ok()?;
+
+    Some(String::from_utf8_lossy(reply.value::<u8>()).to_string())
+}
+
+/// The instance and class from `WM_CLASS`.
+#[must_use]
+pub fn wm_instance_and_class(id: Xid) -> Option<(String, String)> {
+    let wm_class = get_string_property(id, "WM_CLASS")?;
+    let mut parts = wm_class.split('\0');
+
+    Some((parts.next()?.to_string(), parts.next()?.to_string()))
+}
+
+/// Whether a window has a type like "dialog" in its `_NET_WM_WINDOW_TYPE`.
+#[must_use]
+pub fn has_window_type(id: Xid, window_type: &str) -> bool {
+    match intern_atom(&format!(
+        "_NET_WM_WINDOW_TYPE_{}",
+        window_type.to_uppercase()
+    )) {
+        Some(atom) => get_atom_property(id, "_NET_WM_WINDOW_TYPE").contains(&atom),
+        None => false,
+    }
+}
+
+/// The window that a dialog or other short lived window belongs to, from `WM_TRANSIENT_FOR`.
+#[must_use]
+pub fn transient_for(id: Xid) -> Option<Xid> {
+    let cookie = X_DATA.conn.send_request(&xcb::x::GetProperty {
+        delete: false,
+        window: x_window(id),
+        property: xcb::x::ATOM_WM_TRANSIENT_FOR,
+        r#type: xcb::x::ATOM_WINDOW,
+        long_offset: 0,
+        long_length: 1,
+    });
+
+    X_DATA
+        .conn
+        .wait_for_reply(cookie)
+        .ok()?
+        .value::<xcb::x::Window>()
+        .first()
+        .map(|window| window.resource_id())
+        .filter(|parent| *parent != 0)
+}
+
+/// The process id a window says it belongs to in `_NET_WM_PID`.
+#[must_use]
+pub fn window_pid(id: Xid) -> Option<u32> {
+    let property = intern_atom("_NET_WM_PID")?;
+
+    let cookie = X_DATA.conn.send_request(&xcb::x::GetProperty {
+        delete: false,
+        window: x_window(id),
+        property,
+        r#type: xcb::x::ATOM_CARDINAL,
+        long_offset: 0,
+        long_length: 1,
+    });
+
+    X_DATA
+        .conn
+        .wait_for_reply(cookie)
+        .ok()?
+        .value::<u32>()
+        .first()
+        .copied()
+}
+
+/// Whether a window is mapped, so it would be seen if nothing was in front of it.
+#[must_use]
+pub fn is_mapped(id: Xid) -> bool {
+    let cookie = X_DATA.conn.send_request(&xcb::x::GetWindowAttributes {
+        window: x_window(id),
+    });
+
+    X_DATA.conn.wait_for_reply(cookie).map_or(false, |reply| {
+        reply.map_state() == xcb::x::MapState::Viewable
+    })
+}
+
+/// The width of a window's border.
+#[must_use]
+pub fn border_width(id: Xid) -> Option<u32> {
+    let cookie = X_DATA.conn.send_request(&xcb::x::GetGeometry {
+        drawable: xcb::x::Drawable::Window(x_window(id)),
+    });
+
+    X_DATA
+        .conn
+        .wait_for_reply(cookie)
+        .ok()
+        .map(|reply| reply.border_width().into())
+}
+
+/// The name and region of every `RandR` output that is turned on, like `("DP-1", ...)`.
+#[must_use]
+pub fn output_regions() -> Vec<(String, Region)> {
+    let cookie = X_DATA
+        .conn
+        .send_request(&xcb::randr::GetScreenResourcesCurrent {
+            window: X_DATA.root,
+        });
+
+    let resources = match X_DATA.conn.wait_for_reply(cookie) {
+        Ok(resources) => resources,
+        Err(_) => return vec![],
+    };
+    let timestamp = resources.config_timestamp();
+
+    resources
+        .outputs()
+        .iter()
+        .filter_map(|output| {
+            let cookie = X_DATA.conn.send_request(&xcb::randr::GetOutputInfo {
+                output: *output,
+                config_timestamp: timestamp,
+            });
+            let output_info = X_DATA.conn.wait_for_reply(cookie).ok()?;
+
+            // Outputs that are turned off have no crtc.
+            if output_info.crtc().is_none() {
+                return None;
+            }
+
+            let cookie = X_DATA.conn.send_request(&xcb::randr::GetCrtcInfo {
+                crtc: output_info.crtc(),
+                config_timestamp: timestamp,
+            });
+            let crtc_info = X_DATA.conn.wait_for_reply(cookie).ok()?;
+
+            Some((
+                String::from_utf8_lossy(output_info.name()).to_string(),
+                Region::new(
+                    crtc_info.x().try_into().ok()?,
+                    crtc_info.y().try_into().ok()?,
+                    crtc_info.width().into(),
+                    crtc_info.height().into(),
+                ),
+            ))
+        })
+        .collect()
+}